@@ -0,0 +1,105 @@
+//! Settings file. `dice_roulette.toml` in the working directory can change the starting hand,
+//! where scores are saved and how many are kept. Anything it leaves out keeps its default.
+//!
+//! ```toml
+//! dice = 20
+//! sides = 6
+//! score_file = "scores.msgpack"
+//! top_scores = 5
+//! workers = 4
+//! ```
+
+use std::{fs, io, path::Path};
+
+use serde::Deserialize;
+
+pub(crate) const CONFIG_FILE_PATH: &str = "dice_roulette.toml";
+
+/// Fields of the settings file, each optional
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Dice in the starting hand
+    pub dice: Option<i32>,
+    /// Sides of each die
+    pub sides: Option<i8>,
+    /// Where scores are saved, as JSON when named `*.json` (MessagePack otherwise)
+    pub score_file: Option<String>,
+    /// How many of the best scores are kept and listed
+    pub top_scores: Option<usize>,
+    /// Most threads rolling a hand at once, 0 for one per available core
+    pub workers: Option<usize>,
+}
+
+impl Config {
+    /// The config read from path, or an empty config (all defaults) when there is no such file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    /// The config written in text. The error names the line and field that failed to parse
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test fields are read when given and left out otherwise
+    #[test]
+    fn config_fields_parsed() {
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+        assert_eq!(
+            Config::parse(
+                "dice = 20\nsides = 6\nscore_file = \"a.msgpack\"\ntop_scores = 5\nworkers = 4"
+            ),
+            Ok(Config {
+                dice: Some(20),
+                sides: Some(6),
+                score_file: Some("a.msgpack".to_string()),
+                top_scores: Some(5),
+                workers: Some(4),
+            })
+        );
+        assert_eq!(
+            Config::parse("sides = 8"),
+            Ok(Config {
+                sides: Some(8),
+                ..Config::default()
+            })
+        );
+    }
+
+    /// Test malformed configs are rejected naming the field
+    #[test]
+    fn malformed_config_names_field() {
+        let error = Config::parse("dice = 20\nsides = \"six\"").unwrap_err();
+        assert!(error.contains("line 2"), "{error}");
+        assert!(error.contains("sides"), "{error}");
+
+        let error = Config::parse("sides = 300").unwrap_err();
+        assert!(error.contains("sides"), "{error}");
+
+        let error = Config::parse("dices = 20").unwrap_err();
+        assert!(error.contains("unknown field `dices`"), "{error}");
+    }
+
+    /// Test a missing file is the default config, but other read errors aren't
+    #[test]
+    fn missing_config_file_is_default() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join(CONFIG_FILE_PATH);
+        assert_eq!(Config::load(&path), Ok(Config::default()));
+
+        std::fs::write(&path, "top_scores = 3").unwrap();
+        assert_eq!(Config::load(&path).unwrap().top_scores, Some(3));
+
+        assert!(Config::load(directory.path()).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! The game played on tokio tasks instead of threads. A task each rolls the hands, scores the
+//! rounds and presents the updates, passing them along tokio channels in the same order as
+//! game_loop does, so a game rolled from the same dice scores the same.
+
+use std::io::{self, IsTerminal};
+
+use tokio::{
+    runtime::Runtime,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task,
+};
+
+use super::{
+    roll_dice, score_round, DiceHand, DiceRollTotal, GameResult, GameUpdate, RollSource,
+    UpdateTally,
+};
+
+/// As game_loop_with_rolls, with the hand, logic and update stages run as tokio tasks.
+/// Must be awaited within a tokio runtime, as the stages are spawned onto it.
+/// Dice are rolled on the runtime's blocking threads.
+///
+/// ```
+/// use dice_roulette::{game_loop_async, game_loop_with_rolls, DiceHand, RollSource};
+///
+/// let hand = DiceHand::new(3, 6).unwrap();
+/// let runtime = tokio::runtime::Runtime::new().unwrap();
+/// let score = runtime.block_on(game_loop_async(hand, RollSource::seeded(7)));
+/// assert_eq!(score, game_loop_with_rolls(hand, RollSource::seeded(7)));
+/// ```
+pub async fn game_loop_async(starting_hand: DiceHand, rolls: RollSource) -> i64 {
+    play_game_async(starting_hand, rolls).await.final_score
+}
+
+// Play a game on a runtime of its own, for callers outside of any runtime
+pub(crate) fn play_game_blocking(starting_hand: DiceHand, rolls: RollSource) -> GameResult {
+    Runtime::new()
+        .expect("Failed to start the tokio runtime")
+        .block_on(play_game_async(starting_hand, rolls))
+}
+
+// Play the game, tallying its updates as they arrive.
+// Every task of the game has finished by the time its result is returned.
+async fn play_game_async(starting_hand: DiceHand, rolls: RollSource) -> GameResult {
+    let (tx_hand, rx_hand) = mpsc::unbounded_channel();
+    let (tx_total, rx_total) = mpsc::unbounded_channel();
+    let (tx_update, rx_update) = mpsc::unbounded_channel();
+
+    // Send starting value
+    let number_of_dice = starting_hand.number_of_dice;
+    println!("Rolling first hand of {number_of_dice} dice...");
+    tx_hand.send(starting_hand).unwrap();
+
+    // TASKS
+    let hand_task = tokio::spawn(roll_hands(rx_hand, tx_total, tx_update.clone(), rolls));
+    let logic_task = tokio::spawn(play_rounds(starting_hand, rx_total, tx_hand, tx_update));
+    let update_task = tokio::spawn(tally_updates(rx_update));
+
+    let result = update_task.await.expect("Update task panicked");
+    hand_task.await.expect("Hand task panicked");
+    logic_task.await.expect("Logic task panicked");
+    result
+}
+
+/// Roll each hand as it arrives, until a hand fails to roll or no more hands arrive
+async fn roll_hands(
+    mut rx_hand: UnboundedReceiver<DiceHand>,
+    tx_total: UnboundedSender<DiceRollTotal>,
+    tx_update: UnboundedSender<GameUpdate>,
+    rolls: RollSource,
+) {
+    while let Some(hand) = rx_hand.recv().await {
+        // Rolling waits on the dice threads, so keep it off the runtime's workers
+        let rolls = rolls.clone();
+        let rolled = task::spawn_blocking(move || roll_dice(hand, &rolls))
+            .await
+            .expect("Rolling the hand panicked");
+        match rolled {
+            Ok(dice_totals) => {
+                // The logic task is gone once the game is over
+                if tx_total.send(dice_totals).is_err() {
+                    break;
+                }
+            }
+            Err(error) => {
+                // Closing tx_total ends the game with the score so far
+                tx_update
+                    .send(GameUpdate::Message(format!(
+                        "Can't roll the hand: {error}\n"
+                    )))
+                    .unwrap();
+                break;
+            }
+        }
+    }
+}
+
+/// Game logic: score each rolled hand and pass the next hand back to be rolled,
+/// until the even total beats the odd total or no more hands arrive
+async fn play_rounds(
+    starting_hand: DiceHand,
+    mut rx_total: UnboundedReceiver<DiceRollTotal>,
+    tx_hand: UnboundedSender<DiceHand>,
+    tx_update: UnboundedSender<GameUpdate>,
+) {
+    let mut hand = starting_hand;
+    let mut total = 0;
+    let mut round = 0;
+    while let Some(dice_totals) = rx_total.recv().await {
+        round += 1;
+        let (updates, next_hand) = score_round(round, hand, dice_totals, &mut total);
+        for update in updates {
+            tx_update.send(update).unwrap();
+        }
+        let Some(next_hand) = next_hand else {
+            return;
+        };
+        // The hand task is gone once a hand fails to roll
+        if tx_hand.send(next_hand).is_err() {
+            return;
+        }
+        hand = next_hand;
+    }
+}
+
+/// Print game updates as they arrive, until every other stage is done
+async fn tally_updates(mut rx_update: UnboundedReceiver<GameUpdate>) -> GameResult {
+    let status_line = io::stdout().is_terminal();
+    let mut tally = UpdateTally::new(status_line);
+    while let Some(update) = rx_update.recv().await {
+        tally
+            .record(update, &mut io::stdout())
+            .expect("Failed to print game updates");
+    }
+    tally
+        .finish(&mut io::stdout())
+        .expect("Failed to print game updates")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_loop_with_rolls, play_game};
+
+    /// Test seeded games score the same played on tasks as on threads
+    #[test]
+    fn async_game_matches_threaded_game() {
+        let runtime = Runtime::new().unwrap();
+        for (dice, sides) in [(1, 2), (5, 6), (20, 6), (12, 7)] {
+            let hand = DiceHand::new(dice, sides).unwrap();
+            for seed in 0..20 {
+                let threaded = game_loop_with_rolls(hand, RollSource::seeded(seed));
+                let on_tasks = runtime.block_on(game_loop_async(hand, RollSource::seeded(seed)));
+                assert_eq!(on_tasks, threaded, "{dice} {sides}-sided dice, seed {seed}");
+            }
+        }
+    }
+
+    /// Test the rounds played are recorded the same too, outside of any runtime
+    #[test]
+    fn blocking_async_game_counts_rounds() {
+        let hand = DiceHand::new(20, 6).unwrap();
+        for seed in 0..10 {
+            let threaded = play_game(hand, RollSource::seeded(seed));
+            let on_tasks = play_game_blocking(hand, RollSource::seeded(seed));
+            assert_eq!(on_tasks, threaded, "seed {seed}");
+        }
+    }
+
+    /// Test a game whose rolls run out ends with the score so far
+    #[test]
+    fn async_game_ends_when_rolls_run_out() {
+        // 1 and 3 are odd: a second hand of 4 dice, which has no rolls left
+        let hand = DiceHand::new(2, 6).unwrap();
+        let result = play_game_blocking(hand, RollSource::scripted(vec![1, 3]));
+        assert_eq!((result.final_score, result.rounds.len()), (4, 1));
+    }
+}
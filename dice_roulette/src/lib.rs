@@ -0,0 +1,3742 @@
+///////////////////////////////////////////////////////////////////////////////
+//    Copyright 2023 Hayden Mark Sip
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+///////////////////////////////////////////////////////////////////////////////
+
+//! Project Motivation:
+//! Mini automated dice rolling game.
+//! Purpose of this project was to become familiar with rusts async channels.
+
+//! DICE ROULETTE
+//! Create a single player probability 'game'.
+//! A player starts with X dice. Each dice roll adds to the total score.
+//! The objective is to get the highest score!
+//! Each even number rolled, subtracts the number of dice in your 'hand'.
+//! Each odd number rolled, adds to the number of dice in your 'hand'.
+//! When you have less than 1 die in your hand, the game ends and the final score is printed!
+
+mod config;
+#[cfg(feature = "tokio")]
+mod game_async;
+mod menu;
+
+#[cfg(feature = "tokio")]
+pub use game_async::game_loop_async;
+
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+    process::ExitCode,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        mpsc::{self, Receiver, SendError, Sender, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use config::{Config, CONFIG_FILE_PATH};
+use menu::{parse_command, print_menu, write_help, Command};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Updates sent from the game to whoever is presenting it.
+/// Each round sends its Score first, then its RoundSummary, then that round's Messages,
+/// before anything of the next round; rounds are numbered from 1 without gaps.
+/// Presenters may rely on this order, e.g. to show messages under the round they belong to.
+enum GameUpdate {
+    Message(String),
+    /// Points scored in a round, along with the running total that includes them
+    Score {
+        round: u32,
+        points: i64,
+        total: i64,
+    },
+    /// How a round's roll of dice split between even and odd dice,
+    /// and the dice in the next hand (0 once the game is over)
+    RoundSummary {
+        round: u32,
+        dice: i32,
+        even: i64,
+        odd: i64,
+        next_hand: i32,
+    },
+}
+
+/// A finished game: its final score and every round played, in order
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub final_score: i64,
+    pub rounds: Vec<RoundResult>,
+}
+
+/// A round of a game, as it was rolled and scored
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoundResult {
+    /// Dice in the hand rolled this round
+    pub dice_rolled: i32,
+    /// Total of the dice which rolled even
+    pub even: i64,
+    /// Total of the dice which rolled odd
+    pub odd: i64,
+    /// Score of the game up to and including this round
+    pub running_total: i64,
+}
+
+/// Why a game ended
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameOver {
+    /// The even total beat the odd total, leaving no dice in hand
+    Bust,
+    /// Cancelled through a CancelToken, between rounds
+    Cancelled,
+    /// A hand couldn't be rolled (see DiceError)
+    RollFailed,
+}
+
+/// Cooperative cancellation of a game, e.g. from another frontend's thread.
+/// Clones share the token, so any of them can cancel.
+///
+/// ```
+/// use dice_roulette::{game_loop_cancellable, CancelToken, DiceHand, GameOver, RollSource};
+///
+/// // Two odd rolls would go on to roll another hand
+/// let hand = DiceHand::new(2, 6).unwrap();
+/// let rolls = RollSource::scripted(vec![1, 1]);
+/// let cancel = CancelToken::new();
+/// cancel.cancel();
+///
+/// // The game stops after its first round
+/// let (score, reason) = game_loop_cancellable(hand, rolls, &cancel);
+/// assert_eq!((score, reason), (2, GameOver::Cancelled));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask games using this token to stop at the end of their current round
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// One finished game, as appended to the game log
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct GameLogEntry {
+    dice: i32,
+    sides: i8,
+    score: i64,
+    rounds: u32,
+    /// Seconds since the Unix epoch when the game finished
+    timestamp: u64,
+    /// Profile the game was played in, None for the shared scores (and games logged before
+    /// profiles were)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+}
+
+/// The last game played, saved so the replay command can go over it round by round
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct GameTranscript {
+    /// Seed the game's dice were rolled from
+    seed: u64,
+    /// Starting hand of the game
+    dice: i32,
+    sides: i8,
+    result: GameResult,
+}
+
+/// A kept score, with who scored it, and when and how.
+/// Ordered by score first, so the best scores sort last.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct ScoreEntry {
+    score: i64,
+    /// Seconds since the Unix epoch when the game finished, 0 if unknown
+    achieved_at: u64,
+    /// Starting hand of the game, 0 dice if unknown
+    dice: i32,
+    sides: u32,
+    /// Player who scored it, empty if anonymous.
+    /// Last, so entries saved before scores were named still load (as anonymous)
+    #[serde(default)]
+    name: String,
+}
+
+impl From<i64> for ScoreEntry {
+    /// A score from a file written before scores kept their metadata
+    fn from(score: i64) -> Self {
+        Self {
+            score,
+            ..Self::default()
+        }
+    }
+}
+
+impl fmt::Display for ScoreEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.score)?;
+        if !self.name.is_empty() {
+            write!(f, " by {}", self.name)?;
+        }
+        match (self.achieved_at, self.dice) {
+            (0, 0) => Ok(()),
+            (0, dice) => write!(f, "  ({dice} {}-sided dice)", self.sides),
+            (achieved_at, 0) => write!(f, "  ({})", format_date(achieved_at)),
+            (achieved_at, dice) => write!(
+                f,
+                "  ({}, {dice} {}-sided dice)",
+                format_date(achieved_at),
+                self.sides
+            ),
+        }
+    }
+}
+
+/// The UTC date (YYYY-MM-DD) of a time in seconds since the Unix epoch
+fn format_date(seconds: u64) -> String {
+    // Days to a civil date, counting from March so leap days end each 400 year era
+    let days = seconds / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Transmitter of game updates, over either an unbounded or a bounded channel
+#[derive(Clone)]
+enum UpdateSender {
+    Unbounded(Sender<GameUpdate>),
+    /// Blocks the sender while the channel is full
+    Bounded(SyncSender<GameUpdate>),
+}
+
+impl UpdateSender {
+    fn send(&self, update: GameUpdate) -> Result<(), SendError<GameUpdate>> {
+        match self {
+            UpdateSender::Unbounded(tx) => tx.send(update),
+            UpdateSender::Bounded(tx) => tx.send(update),
+        }
+    }
+}
+
+struct Dice {
+    value: i8,
+}
+
+/// Reasons a die can't be rolled
+///
+/// ```
+/// use dice_roulette::DiceError;
+///
+/// let error = DiceError::InvalidSides(0);
+/// assert_eq!(error.to_string(), "dice need at least 1 side, not 0");
+/// ```
+#[derive(Debug, PartialEq)]
+pub enum DiceError {
+    /// A die needs at least one side
+    InvalidSides(i8),
+    /// The rolled value doesn't fit the die's value type (or a scripted value isn't on the die)
+    ValueOutOfRange(i32),
+    /// Dice of the hand which were never rolled, even after retrying
+    MissingDice(i32),
+    /// A scripted roll source ran out of values
+    ScriptExhausted,
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiceError::InvalidSides(sides) => {
+                write!(f, "dice need at least 1 side, not {sides}")
+            }
+            DiceError::ValueOutOfRange(value) => {
+                write!(f, "rolled value {value} is out of range")
+            }
+            DiceError::MissingDice(missing) => {
+                write!(f, "{missing} dice were never rolled")
+            }
+            DiceError::ScriptExhausted => write!(f, "the scripted rolls ran out"),
+        }
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+impl Dice {
+    // Returns a rolled dice with an integer value from 1 to the number of sides
+    fn new(number_of_sides: i8) -> Result<Self, DiceError> {
+        Dice::new_with_rng(&mut rand::thread_rng(), number_of_sides)
+    }
+
+    // Same as new, with the value drawn from rng (so a seeded rng rolls reproducibly)
+    fn new_with_rng<R: Rng>(rng: &mut R, number_of_sides: i8) -> Result<Self, DiceError> {
+        Dice::check_sides(number_of_sides)?;
+
+        // random modulo sides is equivalent to 0..(number_of_sides - 1)
+        // so correct with +1 (in a wider type, so the sum can't wrap)
+        let sides = i16::from(number_of_sides);
+        let roll = rng.gen_range(0..sides) + 1;
+        let value = i8::try_from(roll).map_err(|_| DiceError::ValueOutOfRange(roll.into()))?;
+        debug_assert!((1..=number_of_sides).contains(&value));
+        Ok(Self { value })
+    }
+
+    // Dice can only be rolled with at least one side
+    fn check_sides(number_of_sides: i8) -> Result<(), DiceError> {
+        match number_of_sides {
+            sides if sides < 1 => Err(DiceError::InvalidSides(sides)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where the values of rolled dice come from
+///
+/// ```
+/// use dice_roulette::{game_loop_with_rolls, DiceHand, RollSource};
+///
+/// // Two dice roll 3 and 2, leaving one die (odd leads by 1),
+/// // which rolls 5. Five dice then roll an even lead, ending the game
+/// let rolls = RollSource::scripted(vec![3, 2, 5, 2, 4, 6, 1, 3]);
+/// let hand = DiceHand::builder()
+///     .number_of_dice(2)
+///     .number_of_sides(6)
+///     .build()
+///     .unwrap();
+/// assert_eq!(game_loop_with_rolls(hand, rolls), 26);
+///
+/// // A seeded game replays exactly
+/// let first = game_loop_with_rolls(hand, RollSource::seeded(7));
+/// assert_eq!(game_loop_with_rolls(hand, RollSource::seeded(7)), first);
+/// ```
+#[derive(Clone, Default)]
+pub struct RollSource(Rolls);
+
+#[derive(Clone, Default)]
+enum Rolls {
+    #[default]
+    Random,
+    /// Values not yet rolled, shared by the threads rolling each die
+    Scripted(Arc<Mutex<std::vec::IntoIter<i32>>>),
+    /// Random number generator shared by the threads rolling each die
+    Seeded(Arc<Mutex<StdRng>>),
+}
+
+impl RollSource {
+    /// Dice rolled with the thread-local random number generator
+    pub fn random() -> Self {
+        Self(Rolls::Random)
+    }
+
+    /// Dice take their values from rolls, in order. Rolling past the end is an error
+    /// (DiceError::ScriptExhausted), as is a value outside 1 to the number of sides.
+    pub fn scripted(rolls: Vec<i32>) -> Self {
+        Self(Rolls::Scripted(Arc::new(Mutex::new(rolls.into_iter()))))
+    }
+
+    /// Dice rolled with a random number generator started from seed,
+    /// so a game played with the same seed (and hand) rolls the same values
+    pub fn seeded(seed: u64) -> Self {
+        Self(Rolls::Seeded(Arc::new(Mutex::new(StdRng::seed_from_u64(
+            seed,
+        )))))
+    }
+
+    /// Value of one die of the hand. An exploding die showing its top face is rolled again
+    /// and the rolls added, up to MAX_EXPLOSIONS times
+    fn roll_die(&self, hand: DiceHand) -> Result<i64, DiceError> {
+        let number_of_sides = hand.number_of_sides;
+        match &self.0 {
+            Rolls::Random => explode(hand, || Dice::new(number_of_sides)),
+            Rolls::Scripted(script) => {
+                Dice::check_sides(number_of_sides)?;
+                // Holding the lock keeps each die's rolls together in the script
+                let mut script = script
+                    .lock()
+                    .expect("Rolling never panics with the script locked");
+                explode(hand, || {
+                    let roll = script.next().ok_or(DiceError::ScriptExhausted)?;
+                    match i8::try_from(roll) {
+                        Ok(value) if (1..=number_of_sides).contains(&value) => Ok(Dice { value }),
+                        _ => Err(DiceError::ValueOutOfRange(roll)),
+                    }
+                })
+            }
+            Rolls::Seeded(rng) => {
+                // As with a script, each die's rolls are drawn together
+                let mut rng = rng
+                    .lock()
+                    .expect("Rolling never panics with the generator locked");
+                explode(hand, || Dice::new_with_rng(&mut *rng, number_of_sides))
+            }
+        }
+    }
+}
+
+/// Total of a die rolled with roll, rolling again while an exploding hand's die shows its top face
+fn explode<F>(hand: DiceHand, mut roll: F) -> Result<i64, DiceError>
+where
+    F: FnMut() -> Result<Dice, DiceError>,
+{
+    let mut total = 0;
+    for _ in 0..=MAX_EXPLOSIONS {
+        let value = roll()?.value;
+        total += i64::from(value);
+        if !(hand.exploding && value == hand.number_of_sides) {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// Reasons a hand of dice isn't valid
+#[derive(Debug, PartialEq)]
+pub enum HandError {
+    /// A hand needs at least one die
+    NoDice(i32),
+    /// Dice need at least two sides: one sided dice always roll odd, so the game never ends
+    InvalidSides(i8),
+}
+
+impl fmt::Display for HandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandError::NoDice(dice) => write!(f, "a hand needs at least 1 die, not {dice}"),
+            HandError::InvalidSides(sides) => {
+                write!(f, "dice need at least 2 sides, not {sides}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandError {}
+
+/// The dice held by the player for a round.
+/// Hands are built (and validated) with DiceHand::new or DiceHand::builder()
+///
+/// ```
+/// use dice_roulette::{DiceHand, HandError};
+///
+/// // A dozen six sided dice
+/// let hand = DiceHand::builder().number_of_sides(6).build().unwrap();
+/// assert_eq!(hand.number_of_dice(), 12);
+/// assert_eq!(hand.number_of_sides(), 6);
+/// assert_eq!(DiceHand::new(12, 6), Ok(hand));
+///
+/// // One sided dice would never let the game end
+/// assert_eq!(DiceHand::new(12, 1), Err(HandError::InvalidSides(1)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiceHand {
+    pub(crate) number_of_dice: i32,
+    pub(crate) number_of_sides: i8,
+    /// Dice rolling their top face are rolled again, adding to the die's value
+    pub(crate) exploding: bool,
+    /// Most threads rolling the hand at once, 0 for one per available core
+    pub(crate) workers: usize,
+}
+
+/// The hand the interactive game starts with, and the one a builder starts from
+const STARTING_HAND: DiceHand = DiceHand {
+    number_of_dice: 12,
+    number_of_sides: 7,
+    exploding: false,
+    workers: 0,
+};
+
+impl DiceHand {
+    /// A hand of number_of_dice (plain, non exploding) dice, once validated
+    pub fn new(number_of_dice: i32, number_of_sides: i8) -> Result<DiceHand, HandError> {
+        DiceHand::builder()
+            .number_of_dice(number_of_dice)
+            .number_of_sides(number_of_sides)
+            .build()
+    }
+
+    /// Builder starting from the interactive game's hand of 12 seven sided dice
+    pub fn builder() -> DiceHandBuilder {
+        DiceHandBuilder {
+            hand: STARTING_HAND,
+        }
+    }
+
+    pub fn number_of_dice(&self) -> i32 {
+        self.number_of_dice
+    }
+
+    pub fn number_of_sides(&self) -> i8 {
+        self.number_of_sides
+    }
+
+    pub fn exploding(&self) -> bool {
+        self.exploding
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// A hand is valid with at least one die, each with at least two sides
+    pub fn validate(&self) -> Result<(), HandError> {
+        if self.number_of_dice < 1 {
+            return Err(HandError::NoDice(self.number_of_dice));
+        }
+        if self.number_of_sides < 2 {
+            return Err(HandError::InvalidSides(self.number_of_sides));
+        }
+        Ok(())
+    }
+
+    /// The same kind of dice, with number_of_dice of them in hand
+    pub(crate) fn with_dice(&self, number_of_dice: i32) -> DiceHand {
+        DiceHand {
+            number_of_dice,
+            ..*self
+        }
+    }
+
+    /// The hand for the next round, holding as many dice as the round's parity difference
+    /// (odd - even, clamped to i32::MAX). None when no dice are left: the game is over.
+    ///
+    /// ```
+    /// # use dice_roulette::DiceHand;
+    /// let hand = DiceHand::builder().number_of_sides(6).build().unwrap();
+    ///
+    /// // Odd rolls beat even rolls by 5: five dice next round
+    /// let next_hand = hand.apply_parity(5).unwrap();
+    /// assert_eq!(next_hand.number_of_dice(), 5);
+    /// assert_eq!(next_hand.number_of_sides(), 6);
+    /// // An even lead (or a tie) empties the hand
+    /// assert_eq!(hand.apply_parity(-2), None);
+    /// assert_eq!(hand.apply_parity(0), None);
+    /// ```
+    pub fn apply_parity(&self, parity_difference: i64) -> Option<DiceHand> {
+        match parity_difference.clamp(0, i32::MAX as i64) as i32 {
+            0 => None,
+            number_of_dice => Some(self.with_dice(number_of_dice)),
+        }
+    }
+}
+
+/// Sets up a DiceHand, checking it's valid when built
+///
+/// ```
+/// use dice_roulette::{DiceHand, HandError};
+///
+/// let hand = DiceHand::builder()
+///     .number_of_dice(3)
+///     .number_of_sides(20)
+///     .build();
+/// assert!(hand.is_ok());
+///
+/// let no_dice = DiceHand::builder().number_of_dice(0).build();
+/// assert_eq!(no_dice, Err(HandError::NoDice(0)));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct DiceHandBuilder {
+    hand: DiceHand,
+}
+
+impl DiceHandBuilder {
+    pub fn number_of_dice(mut self, number_of_dice: i32) -> Self {
+        self.hand.number_of_dice = number_of_dice;
+        self
+    }
+
+    pub fn number_of_sides(mut self, number_of_sides: i8) -> Self {
+        self.hand.number_of_sides = number_of_sides;
+        self
+    }
+
+    /// Whether dice explode: a die rolling its top face is rolled again and the rolls added
+    /// (its parity is that of the total)
+    pub fn exploding(mut self, exploding: bool) -> Self {
+        self.hand.exploding = exploding;
+        self
+    }
+
+    /// Most threads rolling the hand at once, however many dice it holds.
+    /// 0 (the default) starts one per available core.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.hand.workers = workers;
+        self
+    }
+
+    /// The hand, once validated
+    pub fn build(self) -> Result<DiceHand, HandError> {
+        self.hand.validate()?;
+        Ok(self.hand)
+    }
+}
+
+/// Totals of a rolled hand: the values of the dice that came up even, and of those that came up odd
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiceRollTotal {
+    pub even: i64,
+    pub odd: i64,
+}
+
+impl DiceRollTotal {
+    /// difference = odd - even
+    /// Parity is the property of being odd or even
+    fn parity_difference(&self) -> i64 {
+        self.odd - self.even
+    }
+
+    pub fn sum(&self) -> i64 {
+        self.odd + self.even
+    }
+}
+
+const SCORE_FILE_PATH: &str = "scores.msgpack";
+const GAME_LOG_PATH: &str = "games.ndjson";
+const TRANSCRIPT_PATH: &str = "last_game.msgpack";
+
+/// How many of the best scores are kept and listed
+const TOP_SCORES: usize = 10;
+
+/// How many times an exploding die can be rolled again, so one sided dice stop exploding
+const MAX_EXPLOSIONS: usize = 10;
+
+/// Hands of more dice than this are rolled one after another on the rolling thread,
+/// rather than across worker threads: past it, passing every die back over a channel
+/// costs more than rolling it. Either way, seeded dice roll the same.
+pub const SEQUENTIAL_ROLL_THRESHOLD: i32 = 10_000;
+
+/// Most rolls waiting to be counted while a hand is rolled, the workers wait beyond it
+const ROLL_CHANNEL_CAPACITY: usize = 1024;
+
+/// How many times roll_dice tries to roll a hand before giving up on lost dice
+const ROLL_ATTEMPTS: usize = 3;
+
+/// How many faces the roll command spins through, and how long each is shown
+const ROLL_ANIMATION_FRAMES: usize = 12;
+const ROLL_ANIMATION_FRAME_TIME: Duration = Duration::from_millis(60);
+
+/// Longest name kept with a score, in characters
+const MAX_NAME_LENGTH: usize = 24;
+
+/// How many times the selftest command rolls its die
+const SELF_TEST_ROLLS: usize = 100_000;
+
+/// Significance levels the selftest command accepts, with the matching upper quantile of
+/// the standard normal distribution
+const SIGNIFICANCE_LEVELS: [(&str, f64); 4] = [
+    ("0.1", 1.281_552),
+    ("0.05", 1.644_854),
+    ("0.01", 2.326_348),
+    ("0.001", 3.090_232),
+];
+
+/// Terminal escape sequence erasing the current line
+const CLEAR_LINE: &str = "\x1b[2K";
+
+/// How a session is set up. Games are built from these settings and the "config" command
+/// prints them, so what is shown is always what is played.
+#[derive(Clone)]
+struct Settings {
+    starting_hand: DiceHand,
+    /// Scores file shared by players without a profile, profiles' files are named after it
+    score_file_path: String,
+    game_log_path: String,
+    /// Where the last game is saved, for the replay command
+    transcript_path: String,
+    /// How many of the best scores are kept and listed
+    top_scores: usize,
+    /// Player whose scores are kept, None for the shared scores
+    profile: Option<String>,
+    /// Seed every game's dice are rolled from, None for a new random seed each game
+    seed: Option<u64>,
+    /// Games are played on tokio tasks rather than threads (needs the tokio feature)
+    async_game: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            starting_hand: STARTING_HAND,
+            score_file_path: SCORE_FILE_PATH.to_string(),
+            game_log_path: GAME_LOG_PATH.to_string(),
+            transcript_path: TRANSCRIPT_PATH.to_string(),
+            top_scores: TOP_SCORES,
+            profile: None,
+            seed: None,
+            async_game: false,
+        }
+    }
+}
+
+impl Settings {
+    /// The default settings, with those given by the config replaced.
+    /// The error names the field with an invalid value.
+    fn from_config(config: Config) -> Result<Self, String> {
+        let defaults = Settings::default();
+        let mut builder = DiceHandBuilder {
+            hand: defaults.starting_hand,
+        };
+        if let Some(dice) = config.dice {
+            builder = builder.number_of_dice(dice);
+        }
+        if let Some(workers) = config.workers {
+            builder = builder.workers(workers);
+        }
+        match config.sides {
+            Some(sides) if sides < 2 => {
+                return Err(format!("sides: dice need at least 2 sides, not {sides}"));
+            }
+            Some(sides) => builder = builder.number_of_sides(sides),
+            None => {}
+        }
+        let starting_hand = builder.build().map_err(|error| format!("dice: {error}"))?;
+        let top_scores = match config.top_scores {
+            Some(0) => return Err("top_scores: at least 1 score must be kept".to_string()),
+            top_scores => top_scores.unwrap_or(defaults.top_scores),
+        };
+        Ok(Self {
+            starting_hand,
+            score_file_path: config.score_file.unwrap_or(defaults.score_file_path),
+            top_scores,
+            ..defaults
+        })
+    }
+}
+
+impl Settings {
+    /// The active profile's scores file: the shared one, or for a profile the shared one
+    /// with `_<profile>` added to its name (scores.msgpack becomes scores_alice.msgpack)
+    fn score_file(&self) -> String {
+        let Some(profile) = &self.profile else {
+            return self.score_file_path.clone();
+        };
+        let (stem, extension) = score_file_name_parts(&self.score_file_path);
+        let path = Path::new(&self.score_file_path);
+        path.with_file_name(format!("{stem}_{profile}{extension}"))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Profiles with a scores file beside the shared one, sorted by name
+    fn list_profiles(&self) -> Vec<String> {
+        let (stem, extension) = score_file_name_parts(&self.score_file_path);
+        let prefix = format!("{stem}_");
+        let directory = match Path::new(&self.score_file_path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return Vec::new();
+        };
+        let mut profiles: Vec<_> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|name| {
+                let profile = name.strip_prefix(&prefix)?.strip_suffix(&extension)?;
+                is_profile_name(profile).then(|| profile.to_string())
+            })
+            .collect();
+        profiles.sort();
+        profiles
+    }
+
+    /// Name of the active profile, as shown to the player
+    fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+}
+
+/// Name standing for the shared scores, rather than a profile of its own
+const DEFAULT_PROFILE: &str = "default";
+
+/// The file name of a scores file split into its stem and extension (with its dot, or empty)
+fn score_file_name_parts(score_file_path: &str) -> (String, String) {
+    let path = Path::new(score_file_path);
+    let stem = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let extension = path.extension().map_or_else(String::new, |extension| {
+        format!(".{}", extension.to_string_lossy())
+    });
+    (stem, extension)
+}
+
+/// Profile names are kept to letters, digits, '-' and '_', so they are safe in a file name
+fn is_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || matches!(character, '-' | '_'))
+}
+
+/// Command line usage of the game
+const USAGE: &str = "Usage: dice_roulette [--dice N] [--sides N] [--seed N] [--async]";
+
+/// Entry point of the interactive game. Runs the menu loop on stdin until the player exits.
+/// Settings are read from dice_roulette.toml in the working directory, if there is one.
+/// The starting hand can then be changed with the arguments `--dice N` and `--sides N`,
+/// and games replayed with `--seed N` (arguments are given without the program name).
+/// With the tokio feature, `--async` plays games on tokio tasks instead of threads.
+/// Exits with a failure status on an invalid config or arguments, or if stdin can't be read.
+pub fn run_dice<I>(args: I) -> ExitCode
+where
+    I: IntoIterator<Item = String>,
+{
+    let config = Config::load(Path::new(CONFIG_FILE_PATH));
+    let settings = match config.and_then(Settings::from_config) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("Invalid config in {CONFIG_FILE_PATH}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let settings = match parse_args(args, settings) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("Invalid arguments: {error}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let stdin = io::stdin();
+    match play_session(&mut stdin.lock(), &settings) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            println!("Failed to read input. Exiting with IO error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Menu loop reading commands from input, saving scores to the settings' score file after each game
+/// and appending the game to its game log.
+/// Ends on "exit" or when the input is exhausted; a read error ends the session with that error.
+/// Scores are saved as soon as each game finishes, so nothing is lost when the session ends early.
+fn play_session<R: BufRead>(input: &mut R, settings: &Settings) -> io::Result<()> {
+    // The session's own copy, as the profile can be switched
+    let mut settings = settings.clone();
+    let game_log_path = settings.game_log_path.clone();
+    let starting_hand = settings.starting_hand;
+
+    // Track best scores in local file. Will save state after each game
+    let mut scores = read_state_from_file(&settings.score_file());
+
+    // Main game loop
+    loop {
+        // For each iteration of the game,
+        // Start with menu and user input
+        print_menu();
+        let Some(user_input) = get_user_input(input)? else {
+            // End of input behaves like "exit"
+            say_goodbye();
+            break;
+        };
+        let Some((command, argument)) = parse_command(&user_input) else {
+            // Misunderstood (or empty) input has no action!
+            continue;
+        };
+        match command {
+            Command::Start => {
+                // Start a new game
+                // Every game is seeded, so any of them can be replayed
+                let seed = settings.seed.unwrap_or_else(rand::random);
+                println!("Starting New Game...");
+                println!("Seed: {seed}");
+                println!();
+
+                let rolls = RollSource::seeded(seed);
+                #[cfg(feature = "tokio")]
+                let result = if settings.async_game {
+                    game_async::play_game_blocking(starting_hand, rolls)
+                } else {
+                    play_game(starting_hand, rolls)
+                };
+                #[cfg(not(feature = "tokio"))]
+                let result = play_game(starting_hand, rolls);
+                let score = result.final_score;
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_secs())
+                    .unwrap_or_default();
+                println!("Game Over!");
+                if score > scores.last().map_or(0, |best| best.score) {
+                    println!("New high score: {}", score);
+                } else {
+                    println!("Total score: {}", score);
+                }
+                println!();
+
+                // Update scores (and save the top scores in file)
+                let default_name = settings.profile.clone().unwrap_or_default();
+                let entry = ScoreEntry {
+                    score,
+                    achieved_at: timestamp,
+                    dice: starting_hand.number_of_dice,
+                    sides: starting_hand.number_of_sides as u32,
+                    name: read_player_name(input, &default_name)?,
+                };
+                insert_score(&mut scores, entry);
+                let saved = save_top_scores(&settings.score_file(), &scores, settings.top_scores);
+                if let Err(error) = saved {
+                    println!("Failed to save scores. Existing with IO error: {}", error);
+                }
+
+                // Keep every game in the log, a failure here shouldn't end the session
+                let entry = GameLogEntry {
+                    dice: starting_hand.number_of_dice,
+                    sides: starting_hand.number_of_sides,
+                    score,
+                    rounds: result.rounds.len() as u32,
+                    timestamp,
+                    profile: settings.profile.clone(),
+                };
+                if let Err(error) = append_game_log(&game_log_path, &entry) {
+                    println!("Failed to log game. {}", error);
+                }
+
+                // Only the last game is kept for replay, apart from the scores
+                let transcript = GameTranscript {
+                    seed,
+                    dice: starting_hand.number_of_dice,
+                    sides: starting_hand.number_of_sides,
+                    result,
+                };
+                if let Err(error) = save_transcript(&settings.transcript_path, &transcript) {
+                    println!("Failed to save the game for replay. {}", error);
+                }
+            }
+            Command::Replay => match read_transcript(&settings.transcript_path) {
+                Some(transcript) => write_transcript(&mut io::stdout(), &transcript)?,
+                None => {
+                    println!();
+                    println!("No previous game recorded");
+                    println!();
+                }
+            },
+            Command::Rules => {
+                print_rules(starting_hand);
+            }
+            Command::Scores => {
+                // Print the top scores (reversed for largest -> smallest)
+                println!();
+                println!("Profile: {}", settings.profile_name());
+                print_top_scores(scores.iter().rev(), settings.top_scores);
+            }
+            Command::Stats => {
+                // Every game is in the log, not only those with a kept score
+                let games: Vec<_> = read_game_log(&game_log_path)
+                    .into_iter()
+                    .filter(|game| game.profile == settings.profile)
+                    .collect();
+                println!();
+                println!("Profile: {}", settings.profile_name());
+                write_game_statistics(&mut io::stdout(), &game_statistics(&games))?;
+            }
+            Command::Seed => match argument {
+                "" => match settings.seed {
+                    Some(seed) => println!("Games are played with seed {seed}"),
+                    None => println!("Games are played with a new random seed each"),
+                },
+                "random" => {
+                    settings.seed = None;
+                    println!("Games will be played with a new random seed each");
+                }
+                seed => match seed.parse() {
+                    Ok(seed) => {
+                        settings.seed = Some(seed);
+                        println!("Games will be played with seed {seed}");
+                    }
+                    Err(_) => println!("Invalid seed '{seed}'. Try: help seed"),
+                },
+            },
+            Command::Restore if argument.is_empty() => {
+                println!("Which file should scores be restored from? Try: help restore");
+            }
+            Command::Restore => match restore_scores(argument, &mut scores) {
+                Ok(count) => {
+                    println!("Restored {count} scores from {argument}");
+                    let saved =
+                        save_top_scores(&settings.score_file(), &scores, settings.top_scores);
+                    if let Err(error) = saved {
+                        println!("Failed to save scores. Existing with IO error: {}", error);
+                    }
+                }
+                Err(error) => println!("Failed to restore scores. {error}"),
+            },
+            Command::Profile => match argument {
+                "" => {
+                    println!();
+                    println!("Active profile: {}", settings.profile_name());
+                    let profiles = settings.list_profiles();
+                    if profiles.is_empty() {
+                        println!("No other profiles yet. Try: profile <name>");
+                    } else {
+                        println!("Profiles: {DEFAULT_PROFILE}, {}", profiles.join(", "));
+                    }
+                    println!();
+                }
+                name if name == DEFAULT_PROFILE || is_profile_name(name) => {
+                    // Scores already saved with the profile they were scored in,
+                    // so only the new profile's scores need loading
+                    settings.profile = (name != DEFAULT_PROFILE).then(|| name.to_string());
+                    let score_file = settings.score_file();
+                    scores = read_state_from_file(&score_file);
+                    println!("Switched to profile {name} (scores in {score_file})");
+                }
+                other => println!(
+                    "Invalid profile name '{other}'. Use letters, digits, '-' and '_'. \
+                     Try: help profile"
+                ),
+            },
+            Command::Roll => {
+                let number_of_sides = starting_hand.number_of_sides;
+                match argument {
+                    "" => {
+                        let animate = io::stdout().is_terminal();
+                        roll_single_die(&mut io::stdout(), number_of_sides, animate)?;
+                    }
+                    "--no-animation" => {
+                        roll_single_die(&mut io::stdout(), number_of_sides, false)?;
+                    }
+                    other => println!("Unknown option '{other}' for roll. Try: help roll"),
+                }
+            }
+            Command::SelfTest => {
+                let level = if argument.is_empty() {
+                    "0.05"
+                } else {
+                    argument
+                };
+                match SIGNIFICANCE_LEVELS.iter().find(|(name, _)| *name == level) {
+                    Some(&(_, quantile)) => {
+                        let number_of_sides = starting_hand.number_of_sides;
+                        let roll = || Dice::new(number_of_sides).map(|dice| dice.value);
+                        match self_test(number_of_sides, SELF_TEST_ROLLS, quantile, roll) {
+                            Ok(Some(result)) => println!(
+                                "Rolled a {number_of_sides}-sided die {SELF_TEST_ROLLS} times: \
+                                 chi-square {:.2}, critical value {:.2} at {level}: {}",
+                                result.statistic,
+                                result.critical_value,
+                                if result.passed() { "PASS" } else { "FAIL" }
+                            ),
+                            Ok(None) => println!("A 1-sided die is always fair!"),
+                            Err(error) => println!("Selftest failed to roll: {error}"),
+                        }
+                    }
+                    None => println!("Unknown significance '{argument}'. Try: help selftest"),
+                }
+            }
+            Command::Config => {
+                write_config(&mut io::stdout(), &settings)?;
+            }
+            Command::Help => {
+                write_help(&mut io::stdout(), argument)?;
+            }
+            Command::Exit => {
+                // End the game
+                say_goodbye();
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn say_goodbye() {
+    println!();
+    println!("Hope you enjoyed the game!");
+    println!();
+}
+
+fn print_rules(starting_hand: DiceHand) {
+    let number_of_dice = starting_hand.number_of_dice;
+    let number_of_sides = starting_hand.number_of_sides;
+
+    // Begin and end with a new line to form isolated paragraph
+    println!();
+    println!("Dice Factions Rules:");
+    println!(concat!(
+        "The objective of this probability game is to get the highest score! ",
+        "To score, the player rolls the dice in their hand. ",
+        "The cummulative value of the roll is added to your score. ",
+        "The same roll is tallied into even and odd scores. ",
+        "The even scores are then subtracted from the odd ",
+        "and the result determines how many dice are in your next hand."
+    ));
+    println!("The player begins the game with {number_of_dice} {number_of_sides}-sided dice in their hand.");
+    println!();
+}
+
+/// The settings changed by command line arguments: the starting hand by `--dice N` and
+/// `--sides N`, the seed of every game by `--seed N`, and `--async` plays games on tokio tasks
+/// (only when built with the tokio feature). Anything not given is kept from settings.
+fn parse_args<I>(args: I, settings: Settings) -> Result<Settings, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut builder = DiceHandBuilder {
+        hand: settings.starting_hand,
+    };
+    let mut seed = settings.seed;
+    let mut async_game = settings.async_game;
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--seed" => seed = Some(parse_flag_value(&flag, args.next())?),
+            "--async" if cfg!(feature = "tokio") => async_game = true,
+            "--async" => return Err("--async needs the game built with the tokio feature".into()),
+            "--dice" => builder = builder.number_of_dice(parse_flag_value(&flag, args.next())?),
+            "--sides" => builder = builder.number_of_sides(parse_flag_value(&flag, args.next())?),
+            other => return Err(format!("unknown option '{other}'")),
+        }
+    }
+    let starting_hand = builder.build().map_err(|error| error.to_string())?;
+    Ok(Settings {
+        starting_hand,
+        seed,
+        async_game,
+        ..settings
+    })
+}
+
+/// The number following a command line flag
+fn parse_flag_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} needs a number"))?;
+    value
+        .parse()
+        .map_err(|_| format!("{flag} needs a whole number in range, not '{value}'"))
+}
+
+/// Roll a single die, writing the face it lands on. When animated, the die first spins
+/// through random faces, redrawn in place on one line.
+fn roll_single_die<W: Write>(out: &mut W, number_of_sides: i8, animate: bool) -> io::Result<i8> {
+    let roll = || {
+        Dice::new(number_of_sides)
+            .map(|dice| dice.value)
+            .map_err(io::Error::other)
+    };
+    if animate {
+        for _ in 0..ROLL_ANIMATION_FRAMES {
+            write!(out, "\r{CLEAR_LINE}Rolling... {}", roll()?)?;
+            out.flush()?;
+            thread::sleep(ROLL_ANIMATION_FRAME_TIME);
+        }
+        write!(out, "\r{CLEAR_LINE}")?;
+    }
+    let value = roll()?;
+    writeln!(out, "You rolled a {value} on a {number_of_sides}-sided die")?;
+    Ok(value)
+}
+
+/// Pearson's chi-square statistic for the number of times each face came up, against a
+/// fair die where every face is equally likely
+pub fn chi_square(face_counts: &[u64]) -> f64 {
+    let total: u64 = face_counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let expected = total as f64 / face_counts.len() as f64;
+    face_counts
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// The chi-square value a fair die exceeds only with the significance matching the normal
+/// quantile, using the Wilson-Hilferty approximation
+fn chi_square_critical_value(degrees_of_freedom: usize, normal_quantile: f64) -> f64 {
+    let k = degrees_of_freedom as f64;
+    let spread = 2.0 / (9.0 * k);
+    k * (1.0 - spread + normal_quantile * spread.sqrt()).powi(3)
+}
+
+/// Outcome of rolling a die many times and testing the faces against a fair die
+#[derive(Debug)]
+struct SelfTest {
+    statistic: f64,
+    critical_value: f64,
+}
+
+impl SelfTest {
+    fn passed(&self) -> bool {
+        self.statistic <= self.critical_value
+    }
+}
+
+/// Roll a die `rolls` times, counting the faces, and test them against a fair die.
+/// A die with a single side has nothing to test.
+fn self_test<F>(
+    number_of_sides: i8,
+    rolls: usize,
+    normal_quantile: f64,
+    mut roll: F,
+) -> Result<Option<SelfTest>, DiceError>
+where
+    F: FnMut() -> Result<i8, DiceError>,
+{
+    Dice::check_sides(number_of_sides)?;
+    if number_of_sides < 2 {
+        return Ok(None);
+    }
+    let mut face_counts = vec![0; number_of_sides as usize];
+    for _ in 0..rolls {
+        let value = roll()?;
+        if !(1..=number_of_sides).contains(&value) {
+            return Err(DiceError::ValueOutOfRange(value.into()));
+        }
+        face_counts[value as usize - 1] += 1;
+    }
+    Ok(Some(SelfTest {
+        statistic: chi_square(&face_counts),
+        critical_value: chi_square_critical_value(face_counts.len() - 1, normal_quantile),
+    }))
+}
+
+/// Write the settings the session plays with in a readable block
+fn write_config<W: Write>(out: &mut W, settings: &Settings) -> io::Result<()> {
+    let DiceHand {
+        number_of_dice,
+        number_of_sides,
+        exploding,
+        workers,
+    } = settings.starting_hand;
+    let exploding = if exploding { "on" } else { "off" };
+
+    // Begin and end with a new line to form isolated paragraph
+    writeln!(out)?;
+    writeln!(out, "Current Configuration:")?;
+    writeln!(
+        out,
+        "  Starting hand: {number_of_dice} {number_of_sides}-sided dice"
+    )?;
+    writeln!(
+        out,
+        "  Scoring rule:  every roll scores, next hand = odd total - even total"
+    )?;
+    writeln!(
+        out,
+        "  Exploding:     {exploding} (at most {MAX_EXPLOSIONS} rerolls per die)"
+    )?;
+    writeln!(
+        out,
+        "  Hand cap:      {} dice",
+        format_thousands(i32::MAX as i64)
+    )?;
+    match workers {
+        0 => writeln!(out, "  Dice threads:  one per core")?,
+        workers => writeln!(out, "  Dice threads:  at most {workers}")?,
+    }
+    writeln!(out, "  Top scores:    {} kept", settings.top_scores)?;
+    writeln!(out, "  Profile:       {}", settings.profile_name())?;
+    writeln!(out, "  Scores file:   {}", settings.score_file())?;
+    writeln!(out, "  Game log:      {}", settings.game_log_path)?;
+    writeln!(out, "  Last game:     {}", settings.transcript_path)?;
+    match settings.seed {
+        Some(seed) => writeln!(out, "  Dice RNG:      seeded with {seed}")?,
+        None => writeln!(out, "  Dice RNG:      seeded randomly for each game")?,
+    }
+    let game_loop = if settings.async_game {
+        "tokio tasks"
+    } else {
+        "threads"
+    };
+    writeln!(out, "  Game loop:     {game_loop}")?;
+    writeln!(out)
+}
+
+/// Prints the first how_many scores of the iterator.
+/// Will print "no scores recorded" if the iterator is empty.
+fn print_top_scores<'a, I>(scores: I, how_many: usize)
+where
+    I: IntoIterator<Item = &'a ScoreEntry>,
+{
+    // Begin and end with a new line to form isolated paragraph
+    println!();
+
+    // Peekable iterator allows checking for empty (without consuming the first item)
+    let mut peekable = scores.into_iter().peekable();
+    if peekable.peek().is_none() {
+        println!("No scores recorded");
+    } else {
+        println!("Top {how_many} Scores:");
+        for (place, score) in peekable.take(how_many).enumerate() {
+            println!("  {}. {score}", place + 1);
+        }
+    }
+    println!();
+}
+
+/// Summary of a set of scores
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ScoreStats {
+    games: usize,
+    mean: f64,
+    /// Middle score, or the mean of the middle two of an even number of scores
+    median: f64,
+    min: i64,
+    max: i64,
+}
+
+/// Summarise scores, in any order. No scores summarise as no games, with every figure 0.
+fn score_statistics(scores: &[i64]) -> ScoreStats {
+    if scores.is_empty() {
+        return ScoreStats::default();
+    }
+    let mut sorted = scores.to_vec();
+    sorted.sort_unstable();
+    let games = sorted.len();
+    // Summed wider, so many large scores can't overflow
+    let sum: i128 = sorted.iter().map(|&score| i128::from(score)).sum();
+    let middle = games / 2;
+    let median = if games.is_multiple_of(2) {
+        (sorted[middle - 1] as f64 + sorted[middle] as f64) / 2.0
+    } else {
+        sorted[middle] as f64
+    };
+    ScoreStats {
+        games,
+        mean: sum as f64 / games as f64,
+        median,
+        min: sorted[0],
+        max: sorted[games - 1],
+    }
+}
+
+/// Summary of a set of games: their scores, and how many rounds they lasted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct GameStats {
+    scores: ScoreStats,
+    mean_rounds: f64,
+}
+
+/// Summarise games, in any order. No games summarise with every figure 0.
+fn game_statistics(games: &[GameLogEntry]) -> GameStats {
+    let scores: Vec<i64> = games.iter().map(|game| game.score).collect();
+    let rounds: u64 = games.iter().map(|game| u64::from(game.rounds)).sum();
+    GameStats {
+        scores: score_statistics(&scores),
+        mean_rounds: if games.is_empty() {
+            0.0
+        } else {
+            rounds as f64 / games.len() as f64
+        },
+    }
+}
+
+/// Write the statistics, or "No games played yet" if there were no games
+fn write_game_statistics<W: Write>(out: &mut W, stats: &GameStats) -> io::Result<()> {
+    let GameStats {
+        scores,
+        mean_rounds,
+    } = stats;
+
+    // Begin and end with a new line to form isolated paragraph
+    writeln!(out)?;
+    if scores.games == 0 {
+        writeln!(out, "No games played yet")?;
+    } else {
+        writeln!(out, "Game Statistics:")?;
+        writeln!(out, "  Games played:  {}", scores.games)?;
+        writeln!(out, "  Mean score:    {:.1}", scores.mean)?;
+        writeln!(out, "  Median score:  {:.1}", scores.median)?;
+        writeln!(out, "  Best score:    {}", format_thousands(scores.max))?;
+        writeln!(out, "  Worst score:   {}", format_thousands(scores.min))?;
+        writeln!(out, "  Mean rounds:   {mean_rounds:.1}")?;
+    }
+    writeln!(out)
+}
+
+/// Write the scores to file_path, replacing the file. Files named `*.json` are written as
+/// (readable, editable) JSON, any others as MessagePack.
+/// Fails if the file can't be created, or the scores can't be encoded into it.
+/// The scores are written to a temporary file beside it first, then renamed over it,
+/// so a save cut short never leaves a truncated score file behind.
+fn save_state_to_file(file_path: &str, scores: &[ScoreEntry]) -> io::Result<()> {
+    let temporary_path = format!("{file_path}.tmp");
+    let write_temporary = || {
+        let mut file = std::fs::File::create(&temporary_path)?;
+        if Path::new(file_path)
+            .extension()
+            .is_some_and(|x| x == "json")
+        {
+            serde_json::to_writer_pretty(&mut file, scores)?;
+        } else {
+            rmp_serde::encode::write(&mut file, scores).map_err(io::Error::other)?;
+        }
+        file.sync_all()
+    };
+    let saved = write_temporary().and_then(|()| replace_file(&temporary_path, file_path));
+    if saved.is_err() {
+        // Leaves the score file as it was; a missing temporary file is fine
+        let _ = std::fs::remove_file(&temporary_path);
+    }
+    saved
+}
+
+/// Rename from over to, replacing it
+fn replace_file(from: &str, to: &str) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        // Windows may refuse to rename over an existing file, so it's removed first
+        Err(_) if cfg!(windows) && Path::new(to).is_file() => {
+            std::fs::remove_file(to)?;
+            std::fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+/// Append the game to the log as a single line of JSON (creating the log if needed)
+fn append_game_log(file_path: &str, entry: &GameLogEntry) -> io::Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    // One write per game keeps lines whole
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_path)?
+        .write_all(line.as_bytes())
+}
+
+/// Every game in the log at file_path, oldest first. A missing log has no games,
+/// and lines that can't be read (e.g. one cut short) are skipped.
+fn read_game_log(file_path: &str) -> Vec<GameLogEntry> {
+    let Ok(log) = std::fs::read_to_string(file_path) else {
+        return Vec::new();
+    };
+    log.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Save the transcript to file_path (as MessagePack), replacing the last game's
+fn save_transcript(file_path: &str, transcript: &GameTranscript) -> io::Result<()> {
+    let bytes = rmp_serde::to_vec(transcript).map_err(io::Error::other)?;
+    std::fs::write(file_path, bytes)
+}
+
+/// The transcript saved in file_path, or None if there is none (or it can't be read)
+fn read_transcript(file_path: &str) -> Option<GameTranscript> {
+    let bytes = std::fs::read(file_path).ok()?;
+    rmp_serde::from_slice(&bytes).ok()
+}
+
+/// Write the transcript's game round by round
+fn write_transcript<W: Write>(out: &mut W, transcript: &GameTranscript) -> io::Result<()> {
+    let GameTranscript {
+        seed,
+        dice,
+        sides,
+        result,
+    } = transcript;
+
+    // Begin and end with a new line to form isolated paragraph
+    writeln!(out)?;
+    writeln!(out, "Last Game: {dice} {sides}-sided dice, seed {seed}")?;
+    for (round, played) in (1..).zip(&result.rounds) {
+        let RoundResult {
+            dice_rolled,
+            even,
+            odd,
+            running_total,
+        } = played;
+        writeln!(
+            out,
+            "  Round {round}: {dice_rolled} dice, {even} even, {odd} odd (total {})",
+            format_thousands(*running_total)
+        )?;
+    }
+    writeln!(
+        out,
+        "Final score: {} after {} rounds",
+        format_thousands(result.final_score),
+        result.rounds.len()
+    )?;
+    writeln!(out)
+}
+
+/// The scores kept in file_path (MessagePack or JSON, whichever it holds),
+/// or none if there is no such file.
+/// Files of bare scores (as saved before scores kept metadata) load with unknown metadata.
+/// A file holding neither is moved aside to `<file_path>.corrupt-<seconds since the epoch>`
+/// with a warning, so the next save doesn't overwrite scores that may yet be recovered
+/// (see restore_scores).
+/// Scores are sorted from worst to best, with repeated scores kept.
+fn read_state_from_file(file_path: &str) -> Vec<ScoreEntry> {
+    let Ok(bytes) = std::fs::read(file_path) else {
+        return Vec::new();
+    };
+    if let Some(mut scores) = decode_scores(&bytes) {
+        scores.sort();
+        return scores;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let corrupt_path = format!("{file_path}.corrupt-{now}");
+    match std::fs::rename(file_path, &corrupt_path) {
+        Ok(()) => println!(
+            "Warning: {file_path} isn't a score file, moved it to {corrupt_path}. \
+             Starting with no scores (once it's repaired, try: help restore)."
+        ),
+        Err(error) => println!(
+            "Warning: {file_path} isn't a score file, and couldn't be moved aside ({error}). \
+             Starting with no scores, saving will replace it."
+        ),
+    }
+    Vec::new()
+}
+
+/// Add the scores of a score file that was moved aside (and since repaired) to scores.
+/// Returns how many were added, or InvalidData if the file still can't be decoded.
+fn restore_scores(backup_path: &str, scores: &mut Vec<ScoreEntry>) -> io::Result<usize> {
+    let bytes = std::fs::read(backup_path)?;
+    let restored = decode_scores(&bytes).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{backup_path} still isn't a score file"),
+        )
+    })?;
+    let count = restored.len();
+    for entry in restored {
+        insert_score(scores, entry);
+    }
+    Ok(count)
+}
+
+/// Save the best top_scores of scores (sorted from worst to best) to file_path
+fn save_top_scores(file_path: &str, scores: &[ScoreEntry], top_scores: usize) -> io::Result<()> {
+    let best: Vec<_> = scores.iter().rev().take(top_scores).cloned().collect();
+    save_state_to_file(file_path, &best)
+}
+
+/// Insert entry into scores sorted from worst to best, after any equal scores
+fn insert_score(scores: &mut Vec<ScoreEntry>, entry: ScoreEntry) {
+    let position = scores.partition_point(|kept| *kept <= entry);
+    scores.insert(position, entry);
+}
+
+/// Scores decoded from MessagePack or JSON, None if the bytes are neither
+fn decode_scores(bytes: &[u8]) -> Option<Vec<ScoreEntry>> {
+    if let Ok(entries) = rmp_serde::from_slice::<Vec<ScoreEntry>>(bytes) {
+        return Some(entries);
+    }
+    if let Ok(values) = rmp_serde::from_slice::<Vec<i64>>(bytes) {
+        return Some(values.into_iter().map(ScoreEntry::from).collect());
+    }
+    if let Ok(entries) = serde_json::from_slice::<Vec<ScoreEntry>>(bytes) {
+        return Some(entries);
+    }
+    if let Ok(values) = serde_json::from_slice::<Vec<i64>>(bytes) {
+        return Some(values.into_iter().map(ScoreEntry::from).collect());
+    }
+    None
+}
+
+/// Asks who scored a finished game, reading their name from input as it was typed.
+/// A blank answer (or none, once the input is exhausted) names them default_name,
+/// which is empty for an anonymous score. Control characters are dropped,
+/// and long names cut to MAX_NAME_LENGTH.
+fn read_player_name<R: BufRead>(input: &mut R, default_name: &str) -> io::Result<String> {
+    let shown_default = if default_name.is_empty() {
+        "anonymous"
+    } else {
+        default_name
+    };
+    print!("Name for the scoreboard [{shown_default}]: ");
+    io::stdout().flush()?;
+    let mut buffer = String::new();
+    input.read_line(&mut buffer)?;
+    println!();
+    let name: String = buffer
+        .trim()
+        .chars()
+        .filter(|character| !character.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    if name.is_empty() {
+        Ok(default_name.to_string())
+    } else {
+        Ok(name)
+    }
+}
+
+/// Reads the next command from input, trimmed and lowercased.
+/// Returns None once the input is exhausted (a blank line is Some(""), not the end).
+fn get_user_input<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut buffer = String::new();
+    match input.read_line(&mut buffer)? {
+        0 => Ok(None),
+        _ => Ok(Some(buffer.trim().to_lowercase())),
+    }
+}
+
+/// Main game loop.
+/// Rolls dice each round. Calculates the total score of the round.
+/// Also determines how many dice are available for the next round.
+/// The game loop ends once the dice held is less than zero.
+/// Returns the final score, along with each round played.
+///
+/// ```
+/// use dice_roulette::{game_loop, DiceHand};
+///
+/// let hand = DiceHand::builder()
+///     .number_of_dice(3)
+///     .number_of_sides(6)
+///     .build()
+///     .unwrap();
+/// // Every die shows at least 1, so the first round alone scores at least 3
+/// let result = game_loop(hand);
+/// assert!(result.final_score >= 3);
+/// assert_eq!(result.rounds[0].dice_rolled, 3);
+/// ```
+pub fn game_loop(starting_hand: DiceHand) -> GameResult {
+    play_game(starting_hand, RollSource::random())
+}
+
+// Play a game over the unbounded update channel, recording its score and rounds
+fn play_game(starting_hand: DiceHand, rolls: RollSource) -> GameResult {
+    let (tx_update, rx_update) = mpsc::channel();
+    let cancel = CancelToken::new();
+    let (result, _) = run_game(
+        starting_hand,
+        rolls,
+        &cancel,
+        UpdateSender::Unbounded(tx_update),
+        rx_update,
+    );
+    result
+}
+
+/// As game_loop, with dice values drawn from rolls (e.g. a fixed script, for an exact replay).
+/// A game whose rolls run out (or fail) ends with the score so far.
+pub fn game_loop_with_rolls(starting_hand: DiceHand, rolls: RollSource) -> i64 {
+    play_game(starting_hand, rolls).final_score
+}
+
+/// As game_loop_with_rolls, but the game checks cancel between rounds, ending early
+/// once it's cancelled. Returns the score so far, and why the game ended.
+/// Every thread of the game has finished by the time this returns.
+pub fn game_loop_cancellable(
+    starting_hand: DiceHand,
+    rolls: RollSource,
+    cancel: &CancelToken,
+) -> (i64, GameOver) {
+    let (tx_update, rx_update) = mpsc::channel();
+    let (result, reason) = run_game(
+        starting_hand,
+        rolls,
+        cancel,
+        UpdateSender::Unbounded(tx_update),
+        rx_update,
+    );
+    (result.final_score, reason)
+}
+
+/// As game_loop, but at most update_capacity game updates are buffered between the
+/// logic thread and the tally loop. The logic thread waits for the tally to catch up
+/// instead of queueing updates without limit. A capacity of 0 hands over each update directly.
+///
+/// ```
+/// use dice_roulette::{game_loop_bounded, DiceHand};
+///
+/// let hand = DiceHand::builder()
+///     .number_of_dice(3)
+///     .number_of_sides(6)
+///     .build()
+///     .unwrap();
+/// assert!(game_loop_bounded(hand, 0) >= 3);
+/// ```
+pub fn game_loop_bounded(starting_hand: DiceHand, update_capacity: usize) -> i64 {
+    let (tx_update, rx_update) = mpsc::sync_channel(update_capacity);
+    let (result, _) = run_game(
+        starting_hand,
+        RollSource::random(),
+        &CancelToken::new(),
+        UpdateSender::Bounded(tx_update),
+        rx_update,
+    );
+    result.final_score
+}
+
+/// A game played a round at a time on the calling thread, with no threads, channels or output.
+/// The rules are those of game_loop: each round scores every die rolled, and the next hand
+/// holds as many dice as the odd total beat the even total by.
+///
+/// ```
+/// use dice_roulette::{DiceHand, GameState, RollSource, RoundOutcome};
+///
+/// let hand = DiceHand::builder()
+///     .number_of_dice(2)
+///     .number_of_sides(6)
+///     .build()
+///     .unwrap();
+/// let mut game = GameState::with_rolls(hand, RollSource::scripted(vec![3, 2, 4]));
+///
+/// // 3 odd beats 2 even by one: a hand of one die
+/// assert!(matches!(game.step(), RoundOutcome::Rolled { round: 1, .. }));
+/// // which rolls 4, even, ending the game
+/// assert!(matches!(game.step(), RoundOutcome::Bust { round: 2, .. }));
+/// assert_eq!(game.score(), 9);
+/// assert!(game.is_over());
+/// ```
+pub struct GameState {
+    /// Hand to roll next, None once the game is over
+    hand: Option<DiceHand>,
+    rolls: RollSource,
+    score: i64,
+    rounds: u32,
+}
+
+/// What came of a GameState step
+#[derive(Debug, PartialEq)]
+pub enum RoundOutcome {
+    /// The round was scored, and the game goes on with next_hand
+    Rolled {
+        round: u32,
+        totals: DiceRollTotal,
+        next_hand: DiceHand,
+    },
+    /// The round was scored, but the even total beat the odd total: the game is over
+    Bust { round: u32, totals: DiceRollTotal },
+    /// The hand couldn't be rolled, the game is over without scoring it
+    RollFailed(DiceError),
+    /// The game was already over, nothing was rolled
+    Finished,
+}
+
+impl GameState {
+    /// A game starting with hand, rolled with random dice
+    pub fn new(hand: DiceHand) -> Self {
+        Self::with_rolls(hand, RollSource::random())
+    }
+
+    /// A game starting with hand, its dice taking their values from rolls
+    pub fn with_rolls(hand: DiceHand, rolls: RollSource) -> Self {
+        Self {
+            hand: Some(hand),
+            rolls,
+            score: 0,
+            rounds: 0,
+        }
+    }
+
+    /// Roll the current hand and score it
+    pub fn step(&mut self) -> RoundOutcome {
+        let Some(hand) = self.hand.take() else {
+            return RoundOutcome::Finished;
+        };
+        let totals = match roll_dice_in_turn(hand, &self.rolls) {
+            Ok(totals) => totals,
+            Err(error) => return RoundOutcome::RollFailed(error),
+        };
+        self.rounds += 1;
+        self.score += totals.sum();
+        self.hand = hand.apply_parity(totals.parity_difference());
+        let round = self.rounds;
+        match self.hand {
+            Some(next_hand) => RoundOutcome::Rolled {
+                round,
+                totals,
+                next_hand,
+            },
+            None => RoundOutcome::Bust { round, totals },
+        }
+    }
+
+    /// Total of every round scored so far
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    /// Rounds scored so far
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    /// The hand the next step rolls, None once the game is over
+    pub fn hand(&self) -> Option<DiceHand> {
+        self.hand
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.hand.is_none()
+    }
+}
+
+/// Roll a hand one die after another on this thread, returning its totals
+/// (roll_dice spreads smaller hands over worker threads instead).
+fn roll_dice_in_turn(hand: DiceHand, rolls: &RollSource) -> Result<DiceRollTotal, DiceError> {
+    Dice::check_sides(hand.number_of_sides)?;
+    let mut totals = DiceRollTotal { even: 0, odd: 0 };
+    for _ in 0..hand.number_of_dice {
+        let roll = rolls.roll_die(hand)?;
+        if roll % 2 == 0 {
+            totals.even += roll;
+        } else {
+            totals.odd += roll;
+        }
+    }
+    Ok(totals)
+}
+
+// Play the game, tallying updates sent through tx_update as they arrive on rx_update.
+// Nothing waits on this thread besides the update channel, so a full bounded channel can't deadlock.
+// The game's threads are joined before returning its result, along with why the game ended.
+fn run_game(
+    starting_hand: DiceHand,
+    rolls: RollSource,
+    cancel: &CancelToken,
+    tx_update: UpdateSender,
+    rx_update: Receiver<GameUpdate>,
+) -> (GameResult, GameOver) {
+    // Transmitter - Reciever structure
+    // Hand thread ... needs reciever that sends the next number of dice to roll
+    // Should accept a number of sides parameter (propagated from game loop input - not yet setup)
+    // Result thread... should recieve DiceRollTotals from Hand thread.
+    // Result thread should then send next dice count to Hand thread
+    // Result thread should send next score back to this (main) thread.
+    // Result is tallied here, when all channels are closed, result the result.
+    let (tx_hand, rx_hand) = mpsc::channel();
+    let (tx_total, rx_total) = mpsc::channel();
+
+    // Hand thread accept rx_hand (to get next hand values),
+    // plus tx_total to send turn values to result thread.
+    // Result thread should take both hand and score transmitters,
+    // plus rx_total to reciece turn values from hand thread
+    // Finally, rx_score remains in this thread to recieve turn scores from result thread.
+
+    // Send starting value
+    let number_of_dice = starting_hand.number_of_dice;
+    println!("Rolling first hand of {number_of_dice} dice...");
+    tx_hand.send(starting_hand).unwrap();
+
+    // THREADS
+    // Manage the hand
+    let tx_error = tx_update.clone();
+    let hand_thread = thread::spawn(move || {
+        for hand in rx_hand {
+            match roll_dice(hand, &rolls) {
+                Ok(dice_totals) => {
+                    // The logic thread is gone once the game is over
+                    if tx_total.send(dice_totals).is_err() {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    // Closing tx_total ends the game with the score so far
+                    tx_error
+                        .send(GameUpdate::Message(format!(
+                            "Can't roll the hand: {error}\n"
+                        )))
+                        .unwrap();
+                    break;
+                }
+            }
+        }
+    });
+
+    // Manage the logic
+    let cancel = cancel.clone();
+    let logic_thread =
+        thread::spawn(move || play_rounds(starting_hand, rx_total, tx_hand, tx_update, &cancel));
+
+    // Tally the score
+    let status_line = io::stdout().is_terminal();
+    let result = tally_updates(rx_update, &mut io::stdout(), status_line)
+        .expect("Failed to print game updates");
+    hand_thread.join().expect("Hand thread panicked");
+    let reason = logic_thread.join().expect("Logic thread panicked");
+    (result, reason)
+}
+
+/// Game logic: score each rolled hand and pass the next hand back to be rolled,
+/// until the even total beats the odd total, the game is cancelled, or no more hands arrive.
+/// Updates for each round are sent in a fixed order, see GameUpdate.
+fn play_rounds(
+    starting_hand: DiceHand,
+    rx_total: Receiver<DiceRollTotal>,
+    tx_hand: Sender<DiceHand>,
+    tx_update: UpdateSender,
+    cancel: &CancelToken,
+) -> GameOver {
+    let mut hand = starting_hand;
+    let mut total = 0;
+    for (round, dice_totals) in (1..).zip(rx_total) {
+        let (updates, next_hand) = score_round(round, hand, dice_totals, &mut total);
+        for update in updates {
+            tx_update.send(update).unwrap();
+        }
+        match next_hand {
+            None => return GameOver::Bust,
+            Some(_) if cancel.is_cancelled() => {
+                tx_update
+                    .send(GameUpdate::Message(
+                        "Game cancelled before rolling it.\n".to_string(),
+                    ))
+                    .unwrap();
+                return GameOver::Cancelled;
+            }
+            Some(next_hand) => {
+                // The hand thread is gone once a hand fails to roll
+                if tx_hand.send(next_hand).is_err() {
+                    return GameOver::RollFailed;
+                }
+                hand = next_hand;
+            }
+        }
+    }
+    GameOver::RollFailed
+}
+
+/// Score the rolled hand as the given round, adding its points to total.
+/// Returns the round's updates (its Score, then its RoundSummary)
+/// along with the next hand, None once the game is over.
+fn score_round(
+    round: u32,
+    hand: DiceHand,
+    dice_totals: DiceRollTotal,
+    total: &mut i64,
+) -> ([GameUpdate; 2], Option<DiceHand>) {
+    // Send the score to be processed
+    *total += dice_totals.sum();
+    let score = GameUpdate::Score {
+        round,
+        points: dice_totals.sum(),
+        total: *total,
+    };
+
+    // Determine the next move in the game (game finished OR roll a new hand of X dice)
+    let next_hand = hand.apply_parity(dice_totals.parity_difference());
+
+    // Update player on even & odd scores, and what comes next
+    let summary = GameUpdate::RoundSummary {
+        round,
+        dice: hand.number_of_dice,
+        even: dice_totals.even,
+        odd: dice_totals.odd,
+        next_hand: next_hand.map_or(0, |next_hand| next_hand.number_of_dice),
+    };
+    ([score, summary], next_hand)
+}
+
+/// Print game updates as they arrive, returning the final total score and each round played.
+/// With status_line, a running total is kept on the last line of output,
+/// rewritten in place (by carriage return) as rounds complete.
+fn tally_updates<W: Write>(
+    rx_update: Receiver<GameUpdate>,
+    out: &mut W,
+    status_line: bool,
+) -> io::Result<GameResult> {
+    let mut tally = UpdateTally::new(status_line);
+    for update in rx_update {
+        tally.record(update, out)?;
+    }
+    tally.finish(out)
+}
+
+/// Running tally of a game's updates, printing each as it's recorded.
+/// Kept apart from any channel, so every way of playing a game presents it the same.
+struct UpdateTally {
+    status_line: bool,
+    total_score: i64,
+    round: u32,
+    /// Rounds summarised so far
+    rounds: Vec<RoundResult>,
+}
+
+impl UpdateTally {
+    fn new(status_line: bool) -> Self {
+        Self {
+            status_line,
+            total_score: 0,
+            round: 0,
+            rounds: Vec::new(),
+        }
+    }
+
+    /// Print update, keeping count of the score and recording each round
+    fn record<W: Write>(&mut self, update: GameUpdate, out: &mut W) -> io::Result<()> {
+        if self.status_line {
+            // clear the status line, messages continue where it was
+            write!(out, "\r{CLEAR_LINE}")?;
+        }
+        match update {
+            GameUpdate::Score {
+                round: score_round,
+                points,
+                total,
+            } => {
+                debug_assert_eq!(score_round, self.round + 1, "Rounds are scored in order");
+                self.round = score_round;
+                self.total_score = total;
+                let round = self.round;
+                let points = format_thousands(points);
+                let total = format_thousands(total);
+                write!(out, "Round {round} — +{points} points (total {total})\n ")?;
+            }
+            GameUpdate::RoundSummary {
+                round: summary_round,
+                dice,
+                even,
+                odd,
+                next_hand,
+            } => {
+                debug_assert_eq!(
+                    summary_round, self.round,
+                    "Summaries follow their round's Score"
+                );
+                self.rounds.push(RoundResult {
+                    dice_rolled: dice,
+                    even,
+                    odd,
+                    running_total: self.total_score,
+                });
+                // each paragraph leaves a trailing space for the next, as messages do
+                write!(
+                    out,
+                    "Rolled total scores of:\n\t{even} even\n\t{odd} odd\n\n "
+                )?;
+                if next_hand == 0 {
+                    write!(
+                        out,
+                        concat!(
+                            "The even score is greater than the odd total this round. ",
+                            "No more dice left in your hand!\n "
+                        )
+                    )?;
+                } else {
+                    write!(out, "Rolling next hand of {next_hand} dice...\n ")?;
+                }
+            }
+            GameUpdate::Message(message) => {
+                // leave a trailing space for the next message
+                write!(out, "{message} ")?;
+            }
+        }
+        if self.status_line {
+            let round = self.round;
+            let total = format_thousands(self.total_score);
+            write!(out, "\r{CLEAR_LINE}[Round {round} | Total {total}]")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// End the game's output, returning the final total score and each round played
+    fn finish<W: Write>(self, out: &mut W) -> io::Result<GameResult> {
+        if self.status_line {
+            write!(out, "\r{CLEAR_LINE}")?;
+        }
+        // leave an empty space after the in-game messages!
+        writeln!(out)?;
+
+        Ok(GameResult {
+            final_score: self.total_score,
+            rounds: self.rounds,
+        })
+    }
+}
+
+/// Format a number with comma thousands separators, e.g. 1204 as "1,204"
+fn format_thousands(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value < 0 {
+        formatted.push('-');
+    }
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Roll a hand of dice, and return the total score of (evens and odds)
+/// The hand's sides are checked before any die is rolled.
+/// Dice lost along the way (e.g. a worker thread panicked) are rolled again.
+/// Each die's value is drawn from rolls.
+/// Hands of more than SEQUENTIAL_ROLL_THRESHOLD dice are rolled on this thread instead.
+fn roll_dice(hand: DiceHand, rolls: &RollSource) -> Result<DiceRollTotal, DiceError> {
+    if hand.number_of_dice > SEQUENTIAL_ROLL_THRESHOLD {
+        return roll_dice_in_turn(hand, rolls);
+    }
+    let spawn = |tx, hand| {
+        spawn_die(tx, hand, rolls.clone());
+    };
+    roll_dice_with(hand, spawn, ROLL_ATTEMPTS)
+}
+
+/// Roll the hand with dice spawned by spawn, re-rolling any that never arrive.
+/// Gives up with DiceError::MissingDice after the given number of attempts,
+/// so a single attempt is strict: any lost die is an error.
+/// A die which can't be rolled fails the hand with its error, once the other dice are in.
+fn roll_dice_with<F>(hand: DiceHand, spawn: F, attempts: usize) -> Result<DiceRollTotal, DiceError>
+where
+    F: Fn(SyncSender<Result<i64, DiceError>>, DiceHand),
+{
+    Dice::check_sides(hand.number_of_sides)?;
+
+    // Collect dice rolls
+    let mut odd_total = 0;
+    let mut even_total = 0;
+    let mut remaining = hand.number_of_dice;
+    for _ in 0..attempts {
+        if remaining <= 0 {
+            break;
+        }
+
+        // Create a channel to pass information back to this thread.
+        // Bounded, so workers wait for rolls to be counted rather than queue the whole hand
+        let (tx, rx) = mpsc::sync_channel(ROLL_CHANNEL_CAPACITY);
+
+        // Spawn dice rolling threads
+        spawn(tx, hand.with_dice(remaining));
+
+        // The channel closes once every worker is done (or gone)
+        let mut roll_error = None;
+        for recieved_roll in rx {
+            remaining -= 1;
+            match recieved_roll {
+                Ok(roll) if (roll % 2) == 0 => {
+                    even_total += roll;
+                }
+                Ok(roll) => {
+                    odd_total += roll;
+                }
+                Err(error) => {
+                    roll_error.get_or_insert(error);
+                }
+            }
+        }
+        if let Some(error) = roll_error {
+            return Err(error);
+        }
+    }
+
+    if remaining > 0 {
+        return Err(DiceError::MissingDice(remaining));
+    }
+    Ok(DiceRollTotal {
+        even: even_total,
+        odd: odd_total,
+    })
+}
+
+// Take ownership of transmitter (limiting its lifetime to the function)
+// Roll (from rolls) restricted between 1 and number_of_sides, on a pool of threads
+// (the hand's workers, or one per available core, but never more than there are dice).
+// Each thread takes dice from a shared count of those left until none are.
+// Returns the number of threads started
+fn spawn_die(tx: SyncSender<Result<i64, DiceError>>, hand: DiceHand, rolls: RollSource) -> i32 {
+    let pool_size = match hand.workers {
+        0 => thread::available_parallelism().map_or(1, |x| x.get()),
+        workers => workers,
+    };
+    let workers = i32::try_from(pool_size)
+        .unwrap_or(i32::MAX)
+        .min(hand.number_of_dice);
+    if workers <= 0 {
+        return 0;
+    }
+    let dice_left = Arc::new(AtomicI32::new(hand.number_of_dice));
+    for _ in 0..workers {
+        let tx_worker = tx.clone();
+        let rolls = rolls.clone();
+        let dice_left = Arc::clone(&dice_left);
+        thread::spawn(move || {
+            // Taking a die past the last leaves the count below zero, which is just as empty
+            while dice_left.fetch_sub(1, Ordering::Relaxed) > 0 {
+                tx_worker.send(rolls.roll_die(hand)).unwrap();
+            }
+        });
+    }
+    workers
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Default settings, with files kept in directory
+    fn test_settings(directory: &std::path::Path) -> Settings {
+        let path = |file_name| directory.join(file_name).to_str().unwrap().to_string();
+        Settings {
+            score_file_path: path("scores.msgpack"),
+            game_log_path: path("games.ndjson"),
+            transcript_path: path("last_game.msgpack"),
+            ..Settings::default()
+        }
+    }
+
+    /// Test that the number of dice rolled
+    /// matches the number of dice given to roll
+    #[test]
+    fn number_of_dice_rolled() {
+        // Restrict randomisation to always return a value '1'
+        let dice_to_roll_first = 8;
+        let dice_to_roll_second = 150_000;
+        let number_of_sides = 1;
+
+        assert_eq!(
+            roll_dice(
+                DiceHand {
+                    number_of_dice: dice_to_roll_first,
+                    number_of_sides,
+                    exploding: false,
+                    workers: 0,
+                },
+                &RollSource::random()
+            )
+            .unwrap(),
+            DiceRollTotal {
+                even: 0,
+                odd: dice_to_roll_first as i64,
+            }
+        );
+
+        // Perform a much more demanding roll
+        // (Also checking result isn't a fluke)
+        assert_eq!(
+            roll_dice(
+                DiceHand {
+                    number_of_dice: dice_to_roll_second,
+                    number_of_sides,
+                    exploding: false,
+                    workers: 0,
+                },
+                &RollSource::random()
+            )
+            .unwrap(),
+            DiceRollTotal {
+                even: 0,
+                odd: dice_to_roll_second as i64,
+            }
+        );
+    }
+
+    /// Test the hand grows (or shrinks) to the parity difference, keeping its sides
+    #[test]
+    fn hand_follows_parity_difference() {
+        let hand = DiceHand {
+            number_of_dice: 12,
+            number_of_sides: 7,
+            exploding: false,
+            workers: 0,
+        };
+
+        assert_eq!(
+            hand.with_dice(3),
+            DiceHand {
+                number_of_dice: 3,
+                number_of_sides: 7,
+                exploding: false,
+                workers: 0,
+            }
+        );
+        assert_eq!(hand.apply_parity(20), Some(hand.with_dice(20)));
+        assert_eq!(hand.apply_parity(1), Some(hand.with_dice(1)));
+    }
+
+    /// Test the game ends once the even total catches the odd total
+    #[test]
+    fn hand_empties_without_odd_lead() {
+        let hand = DiceHand {
+            number_of_dice: 12,
+            number_of_sides: 7,
+            exploding: false,
+            workers: 0,
+        };
+
+        assert_eq!(hand.apply_parity(0), None);
+        assert_eq!(hand.apply_parity(-5), None);
+        assert_eq!(hand.apply_parity(i64::MIN), None);
+    }
+
+    /// Test huge differences are clamped to the largest hand
+    #[test]
+    fn hand_growth_is_clamped() {
+        let hand = DiceHand {
+            number_of_dice: 12,
+            number_of_sides: 7,
+            exploding: false,
+            workers: 0,
+        };
+
+        assert_eq!(hand.apply_parity(i64::MAX), Some(hand.with_dice(i32::MAX)));
+        assert_eq!(
+            hand.apply_parity(i32::MAX as i64 + 1),
+            Some(hand.with_dice(i32::MAX))
+        );
+    }
+
+    /// Test rolls stay within the smallest and largest dice
+    #[test]
+    fn dice_values_at_side_boundaries() {
+        for number_of_sides in [1, 2, i8::MAX] {
+            for _ in 0..1000 {
+                let value = Dice::new(number_of_sides).unwrap().value;
+                assert!((1..=number_of_sides).contains(&value));
+            }
+        }
+
+        // The largest face can be rolled without wrapping
+        let largest = (0..10_000).map(|_| Dice::new(2).unwrap().value).max();
+        assert_eq!(largest, Some(2));
+    }
+
+    /// Test dice without sides are an error rather than a panic
+    #[test]
+    fn dice_without_sides_rejected() {
+        assert_eq!(Dice::new(0).err(), Some(DiceError::InvalidSides(0)));
+        assert_eq!(Dice::new(-1).err(), Some(DiceError::InvalidSides(-1)));
+        assert_eq!(
+            Dice::new(i8::MIN).err(),
+            Some(DiceError::InvalidSides(i8::MIN))
+        );
+
+        // Rolling a hand checks before any die is rolled
+        let hand = DiceHand {
+            number_of_dice: 3,
+            number_of_sides: 0,
+            exploding: false,
+            workers: 0,
+        };
+        assert_eq!(
+            roll_dice(hand, &RollSource::random()),
+            Err(DiceError::InvalidSides(0))
+        );
+    }
+
+    /// Test a die lost by its worker is rolled again, or reported in strict mode
+    #[test]
+    fn lost_dice_rerolled() {
+        let hand = DiceHand {
+            number_of_dice: 10,
+            number_of_sides: 1,
+            exploding: false,
+            workers: 0,
+        };
+
+        // The first spawn loses a die, as if its worker thread panicked
+        let drop_one_die = || {
+            let dropped = std::sync::atomic::AtomicBool::new(false);
+            move |tx: SyncSender<Result<i64, DiceError>>, hand: DiceHand| {
+                let rolls = RollSource::random();
+                if dropped.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    spawn_die(tx, hand, rolls);
+                } else {
+                    spawn_die(tx, hand.with_dice(hand.number_of_dice - 1), rolls);
+                }
+            }
+        };
+
+        assert_eq!(
+            roll_dice_with(hand, drop_one_die(), ROLL_ATTEMPTS),
+            Ok(DiceRollTotal { even: 0, odd: 10 })
+        );
+        assert_eq!(
+            roll_dice_with(hand, drop_one_die(), 1),
+            Err(DiceError::MissingDice(1))
+        );
+
+        // Workers which never roll anything exhaust the retries
+        let no_dice = |_: SyncSender<Result<i64, DiceError>>, _: DiceHand| {};
+        assert_eq!(
+            roll_dice_with(hand, no_dice, ROLL_ATTEMPTS),
+            Err(DiceError::MissingDice(10))
+        );
+    }
+
+    /// Test even and odd counting works
+    #[test]
+    fn even_and_odd_split() {
+        // Roll single die, to ensure the result is always between the 'number of sides'
+        // Do this a significant amount of times
+        const NUMBER_OF_ATTEMPTS: i32 = 1000;
+        const NUMBER_OF_SIDES: i8 = 12;
+        const STARTING_HAND: DiceHand = DiceHand {
+            number_of_dice: 1,
+            number_of_sides: NUMBER_OF_SIDES,
+            exploding: false,
+            workers: 0,
+        };
+
+        for _ in 0..NUMBER_OF_ATTEMPTS {
+            let roll_total = roll_dice(STARTING_HAND, &RollSource::random()).unwrap();
+            match roll_total {
+                // Check an even number was rolled
+                DiceRollTotal {
+                    even: even @ 1..=12, // 2, 4, 6...
+                    odd: 0,
+                } if even % 2 == 0 => {
+                    continue;
+                }
+                // Check an odd number was rolled
+                DiceRollTotal {
+                    even: 0,
+                    odd: odd @ 1..=12, // 1, 3, 5...
+                } if odd % 2 == 1 => {
+                    continue;
+                }
+                // Fail case
+                DiceRollTotal { even, odd } => {
+                    unreachable!("(Even: {even}, Odd: {odd})")
+                }
+            }
+        }
+    }
+
+    /// Test simple game begin & end logic. Check for expected scores!
+    #[test]
+    fn game_logic_test() {
+        // Sides > 1 : otherwise causes an infinite game loop...
+        // Start with a simple game, 1 die, 2 sides
+        // Will exit as soon as a 2 is rolled, therefore 2 is the minimum score
+        match game_loop(DiceHand::new(2, 2).unwrap()).final_score {
+            x if x < 2 => {
+                unreachable!("Result for 1 die of 2 sides must be at least 2");
+            }
+            _ => {
+                // must be greater than or equal to 3
+            }
+        }
+
+        // Repeat the experiment for more dice!
+        // Minimum score of 8 (4 roll 1, 2 roll 2 => diff == 0)
+        // Repeat this many times to estimate successful implementation
+        for _ in 1..100 {
+            match game_loop(DiceHand::new(6, 2).unwrap()).final_score {
+                x if x < 8 => {
+                    unreachable!("Result for 6 die of 2 sides must be at least 8");
+                }
+                _ => {
+                    // must be greater than or equal to 3
+                }
+            }
+        }
+    }
+
+    /// Test the rounds of a game are recorded in order, each following from the last
+    #[test]
+    fn game_loop_records_rounds() {
+        for _ in 0..20 {
+            let result = game_loop(DiceHand::new(6, 4).unwrap());
+            let (first, last) = (result.rounds[0], result.rounds[result.rounds.len() - 1]);
+            assert_eq!(first.dice_rolled, 6);
+            assert_eq!(first.running_total, first.even + first.odd);
+            assert_eq!(last.running_total, result.final_score);
+            // the game only goes on while odd beats even
+            assert!(last.even >= last.odd);
+            for pair in result.rounds.windows(2) {
+                let (round, next) = (pair[0], pair[1]);
+                assert!(round.odd > round.even);
+                assert_eq!(next.dice_rolled as i64, round.odd - round.even);
+                // every die scores at least 1, so running totals keep rising
+                assert!(next.running_total >= round.running_total + next.dice_rolled as i64);
+            }
+        }
+    }
+
+    /// Test score file saving state
+    #[test]
+    fn score_state_test() {
+        const FILE_PATH: &str = "test_scores.msgpack";
+
+        // Stored scores in file will be read in ascending order 
+        let scores = vec!(50,30,20,25,27,35);
+        let entries: Vec<_> = scores.into_iter().map(ScoreEntry::from).collect();
+        save_state_to_file(FILE_PATH, &entries).unwrap();
+
+        let buffer = read_state_from_file(FILE_PATH);
+        let mut it = buffer.iter().cloned();
+        if let Some(value) = it.next() {
+            assert_eq!(value.score, 20);
+        } else {
+            panic!("No values read from file");
+        }
+    }
+
+    /// Test the menu loop ends cleanly once its input is exhausted
+    #[test]
+    fn session_ends_at_end_of_input() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+
+        // One game, then the input runs dry without an "exit"
+        let mut input = io::Cursor::new("start\n");
+        assert!(play_session(&mut input, &settings).is_ok());
+
+        // The finished game was saved
+        assert_eq!(read_state_from_file(&settings.score_file_path).len(), 1);
+    }
+
+    /// Test an input closed from the start ends the session straight away
+    #[test]
+    fn session_on_closed_input_ends() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+
+        assert!(play_session(&mut io::empty(), &settings).is_ok());
+        assert!(read_state_from_file(&settings.score_file_path).is_empty());
+    }
+
+    /// Test blank lines re-show the menu rather than ending the session
+    #[test]
+    fn blank_line_is_not_end_of_input() {
+        let mut input = io::Cursor::new("\n\nexit\n");
+        assert_eq!(get_user_input(&mut input).unwrap(), Some(String::new()));
+        assert_eq!(get_user_input(&mut input).unwrap(), Some(String::new()));
+        assert_eq!(
+            get_user_input(&mut input).unwrap(),
+            Some("exit".to_string())
+        );
+        assert_eq!(get_user_input(&mut input).unwrap(), None);
+    }
+
+    /// Test a failing reader ends the session with its error
+    #[test]
+    fn session_returns_read_error() {
+        struct BrokenInput;
+        impl io::Read for BrokenInput {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("stdin closed unexpectedly"))
+            }
+        }
+
+        let directory = tempfile::tempdir().unwrap();
+        let mut input = io::BufReader::new(BrokenInput);
+        assert!(play_session(&mut input, &test_settings(directory.path())).is_err());
+    }
+
+    /// Test a large game runs to completion through a tiny update buffer
+    #[test]
+    fn bounded_update_channel_game() {
+        // Every die scores at least 1, so the first round alone guarantees the hand size
+        let score = game_loop_bounded(
+            DiceHand {
+                number_of_dice: 1000,
+                number_of_sides: 7,
+                exploding: false,
+                workers: 0,
+            },
+            1,
+        );
+        assert!(score >= 1000);
+
+        // A zero capacity channel hands over each update directly
+        let score = game_loop_bounded(
+            DiceHand {
+                number_of_dice: 6,
+                number_of_sides: 2,
+                exploding: false,
+                workers: 0,
+            },
+            0,
+        );
+        assert!(score >= 8);
+    }
+
+    /// Test the thousands separator at its boundaries
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1000), "1,000");
+        assert_eq!(format_thousands(1_234_567), "1,234,567");
+        assert_eq!(format_thousands(-999), "-999");
+        assert_eq!(format_thousands(-1000), "-1,000");
+        assert_eq!(format_thousands(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    /// Test each round reports its points and the running total
+    #[test]
+    fn running_total_transcript() {
+        let (tx_update, rx_update) = mpsc::channel();
+        for update in [
+            GameUpdate::Score {
+                round: 1,
+                points: 86,
+                total: 86,
+            },
+            GameUpdate::RoundSummary {
+                round: 1,
+                dice: 20,
+                even: 40,
+                odd: 44,
+                next_hand: 4,
+            },
+            GameUpdate::Score {
+                round: 2,
+                points: 1118,
+                total: 1204,
+            },
+            GameUpdate::RoundSummary {
+                round: 2,
+                dice: 4,
+                even: 600,
+                odd: 518,
+                next_hand: 0,
+            },
+            GameUpdate::Message("Well played!\n".to_string()),
+        ] {
+            tx_update.send(update).unwrap();
+        }
+        drop(tx_update);
+
+        let mut transcript = Vec::new();
+        let result = tally_updates(rx_update, &mut transcript, false).unwrap();
+        assert_eq!(result.final_score, 1204);
+        assert_eq!(
+            result.rounds,
+            [
+                RoundResult {
+                    dice_rolled: 20,
+                    even: 40,
+                    odd: 44,
+                    running_total: 86,
+                },
+                RoundResult {
+                    dice_rolled: 4,
+                    even: 600,
+                    odd: 518,
+                    running_total: 1204,
+                },
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(transcript).unwrap(),
+            concat!(
+                "Round 1 — +86 points (total 86)\n ",
+                "Rolled total scores of:\n\t40 even\n\t44 odd\n\n ",
+                "Rolling next hand of 4 dice...\n ",
+                "Round 2 — +1,118 points (total 1,204)\n ",
+                "Rolled total scores of:\n\t600 even\n\t518 odd\n\n ",
+                "The even score is greater than the odd total this round. ",
+                "No more dice left in your hand!\n ",
+                "Well played!\n \n"
+            )
+        );
+    }
+
+    /// Test the status line is redrawn after each update and cleared at the end
+    #[test]
+    fn running_total_status_line() {
+        let (tx_update, rx_update) = mpsc::channel();
+        tx_update
+            .send(GameUpdate::Score {
+                round: 1,
+                points: 1500,
+                total: 1500,
+            })
+            .unwrap();
+        drop(tx_update);
+
+        let mut transcript = Vec::new();
+        tally_updates(rx_update, &mut transcript, true).unwrap();
+        let transcript = String::from_utf8(transcript).unwrap();
+        assert!(transcript.contains("\r\x1b[2K[Round 1 | Total 1,500]"));
+        assert!(transcript.ends_with("\r\x1b[2K\n"));
+    }
+
+    /// Test each finished game appends one line to the game log
+    #[test]
+    fn game_log_line_per_game() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+
+        let mut input = io::Cursor::new("start\n\nstart\n\nexit\n");
+        play_session(&mut input, &settings).unwrap();
+
+        let log = std::fs::read_to_string(&settings.game_log_path).unwrap();
+        let games: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(games.len(), 2);
+        for game in games {
+            assert_eq!(game["dice"], 12);
+            assert_eq!(game["sides"], 7);
+            assert!(game["score"].as_i64().unwrap() >= 12);
+            assert!(game["rounds"].as_u64().unwrap() >= 1);
+        }
+    }
+
+    /// Test the last game is saved for replay, and a missing or corrupt one isn't replayed
+    #[test]
+    fn last_game_saved_for_replay() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+        assert_eq!(read_transcript(&settings.transcript_path), None);
+
+        let mut input = io::Cursor::new("seed 5\nstart\n\nreplay\nexit\n");
+        play_session(&mut input, &settings).unwrap();
+        let transcript = read_transcript(&settings.transcript_path).unwrap();
+        assert_eq!(
+            (transcript.seed, transcript.dice, transcript.sides),
+            (5, 12, 7)
+        );
+        let scores = read_state_from_file(&settings.score_file_path);
+        assert_eq!(transcript.result.final_score, scores[0].score);
+        assert_eq!(
+            transcript.result,
+            play_game(STARTING_HAND, RollSource::seeded(5))
+        );
+
+        let transcript = GameTranscript {
+            seed: 1,
+            dice: 2,
+            sides: 6,
+            result: GameResult {
+                final_score: 9,
+                rounds: vec![
+                    RoundResult {
+                        dice_rolled: 2,
+                        even: 2,
+                        odd: 3,
+                        running_total: 5,
+                    },
+                    RoundResult {
+                        dice_rolled: 1,
+                        even: 4,
+                        odd: 0,
+                        running_total: 9,
+                    },
+                ],
+            },
+        };
+        let mut replay = Vec::new();
+        write_transcript(&mut replay, &transcript).unwrap();
+        assert_eq!(
+            String::from_utf8(replay).unwrap(),
+            concat!(
+                "\nLast Game: 2 6-sided dice, seed 1\n",
+                "  Round 1: 2 dice, 2 even, 3 odd (total 5)\n",
+                "  Round 2: 1 dice, 4 even, 0 odd (total 9)\n",
+                "Final score: 9 after 2 rounds\n\n"
+            )
+        );
+
+        std::fs::write(&settings.transcript_path, [0xc1, 0x93]).unwrap();
+        assert_eq!(read_transcript(&settings.transcript_path), None);
+        let mut input = io::Cursor::new("replay\nexit\n");
+        assert!(play_session(&mut input, &settings).is_ok());
+    }
+
+    /// Test a game log that can't be written doesn't end the session
+    #[test]
+    fn game_log_failure_is_not_fatal() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            // A directory can't be opened for appending
+            game_log_path: directory.path().to_str().unwrap().to_string(),
+            ..test_settings(directory.path())
+        };
+
+        let mut input = io::Cursor::new("start\n\nexit\n");
+        assert!(play_session(&mut input, &settings).is_ok());
+        assert_eq!(read_state_from_file(&settings.score_file_path).len(), 1);
+    }
+
+    /// Test the config command shows the settings actually in use
+    #[test]
+    fn config_reflects_settings() {
+        let settings = Settings {
+            starting_hand: DiceHand {
+                number_of_dice: 20,
+                number_of_sides: 6,
+                exploding: false,
+                workers: 0,
+            },
+            score_file_path: "custom_scores.msgpack".to_string(),
+            game_log_path: "custom_games.ndjson".to_string(),
+            transcript_path: "custom_last_game.msgpack".to_string(),
+            top_scores: 3,
+            profile: None,
+            seed: Some(42),
+            async_game: false,
+        };
+
+        let mut config = Vec::new();
+        write_config(&mut config, &settings).unwrap();
+        let config = String::from_utf8(config).unwrap();
+        assert!(config.contains("Starting hand: 20 6-sided dice"));
+        assert!(config.contains("Scores file:   custom_scores.msgpack"));
+        assert!(config.contains("Game log:      custom_games.ndjson"));
+        assert!(config.contains("Top scores:    3 kept"));
+        assert!(config.contains("Dice RNG:      seeded with 42"));
+        assert!(config.contains("Game loop:     threads"));
+    }
+
+    /// Test each round's updates arrive as its Score followed by its RoundSummary
+    #[test]
+    fn round_updates_in_order() {
+        let (tx_total, rx_total) = mpsc::channel();
+        let (tx_hand, rx_hand) = mpsc::channel();
+        let (tx_update, rx_update) = mpsc::channel();
+
+        // Odd lead of 3 then an even lead ends the game on round 2
+        tx_total.send(DiceRollTotal { even: 10, odd: 13 }).unwrap();
+        tx_total.send(DiceRollTotal { even: 8, odd: 2 }).unwrap();
+        drop(tx_total);
+
+        let starting_hand = DiceHand {
+            number_of_dice: 5,
+            number_of_sides: 6,
+            exploding: false,
+            workers: 0,
+        };
+        let reason = play_rounds(
+            starting_hand,
+            rx_total,
+            tx_hand,
+            UpdateSender::Unbounded(tx_update),
+            &CancelToken::new(),
+        );
+        assert_eq!(reason, GameOver::Bust);
+
+        let updates: Vec<String> = rx_update
+            .iter()
+            .map(|update| match update {
+                GameUpdate::Score {
+                    round,
+                    points,
+                    total,
+                } => format!("score {round} {points} {total}"),
+                GameUpdate::RoundSummary {
+                    round,
+                    dice,
+                    even,
+                    odd,
+                    next_hand,
+                } => format!("summary {round} {dice} {even} {odd} {next_hand}"),
+                GameUpdate::Message(message) => message.lines().next().unwrap().to_string(),
+            })
+            .collect();
+        assert_eq!(
+            updates,
+            [
+                "score 1 23 23",
+                "summary 1 5 10 13 3",
+                "score 2 10 33",
+                "summary 2 3 8 2 0",
+            ]
+        );
+        assert_eq!(
+            rx_hand.iter().collect::<Vec<_>>(),
+            [starting_hand.with_dice(3)]
+        );
+    }
+
+    /// Test a hand builds from the starting hand, with the fields set
+    #[test]
+    fn hand_builder_sets_fields() {
+        assert_eq!(DiceHand::builder().build(), Ok(STARTING_HAND));
+
+        let hand = DiceHand::builder()
+            .number_of_dice(3)
+            .number_of_sides(20)
+            .build()
+            .unwrap();
+        assert_eq!(hand.number_of_dice(), 3);
+        assert_eq!(hand.number_of_sides(), 20);
+    }
+
+    /// Test hands without dice, or with sideless dice, aren't built
+    #[test]
+    fn hand_builder_validates() {
+        let builder = DiceHand::builder();
+        assert_eq!(builder.number_of_dice(0).build(), Err(HandError::NoDice(0)));
+        assert_eq!(
+            builder.number_of_dice(-4).build(),
+            Err(HandError::NoDice(-4))
+        );
+        assert_eq!(
+            builder.number_of_sides(0).build(),
+            Err(HandError::InvalidSides(0))
+        );
+        assert_eq!(
+            builder.number_of_sides(-1).build(),
+            Err(HandError::InvalidSides(-1))
+        );
+    }
+
+    /// Test hands from DiceHand::new are validated, one sided dice included
+    #[test]
+    fn hand_new_validates() {
+        assert_eq!(DiceHand::new(5, 0), Err(HandError::InvalidSides(0)));
+        assert_eq!(DiceHand::new(5, 1), Err(HandError::InvalidSides(1)));
+        assert_eq!(DiceHand::new(0, 6), Err(HandError::NoDice(0)));
+        assert_eq!(
+            DiceHand::new(5, 2),
+            Ok(DiceHand {
+                number_of_dice: 5,
+                number_of_sides: 2,
+                exploding: false,
+                workers: 0,
+            })
+        );
+    }
+
+    /// Test a scripted game scores exactly the rolls it was given
+    #[test]
+    fn scripted_game_is_exact() {
+        let hand = DiceHand {
+            number_of_dice: 2,
+            number_of_sides: 6,
+            exploding: false,
+            workers: 0,
+        };
+        // Rounds: 3 + 2 (one die left), 5 (five dice), 2 + 4 + 6 + 1 + 3 (even leads)
+        let rolls = RollSource::scripted(vec![3, 2, 5, 2, 4, 6, 1, 3]);
+        let result = play_game(hand, rolls);
+        assert_eq!(result.final_score, 26);
+        assert_eq!(result.rounds.len(), 3);
+    }
+
+    /// Test running out of scripted rolls, or scripting values off the die, fails the hand
+    #[test]
+    fn scripted_rolls_exhausted_or_out_of_range() {
+        let hand = DiceHand {
+            number_of_dice: 2,
+            number_of_sides: 6,
+            exploding: false,
+            workers: 0,
+        };
+        let rolls = RollSource::scripted(vec![1]);
+        assert_eq!(roll_dice(hand, &rolls), Err(DiceError::ScriptExhausted));
+        let rolls = RollSource::scripted(vec![1, 7]);
+        assert_eq!(roll_dice(hand, &rolls), Err(DiceError::ValueOutOfRange(7)));
+
+        // The game ends with the score so far
+        let rolls = RollSource::scripted(vec![5, 4]);
+        let result = play_game(hand, rolls);
+        assert_eq!(result.final_score, 9);
+        assert_eq!(result.rounds.len(), 1);
+    }
+
+    /// Test a game cancelled from another thread stops between rounds, with the threads joined
+    #[test]
+    fn cancelled_game_stops() {
+        // One sided dice always roll odd, so the game never ends by itself
+        let hand = DiceHand {
+            number_of_dice: 3,
+            number_of_sides: 1,
+            exploding: false,
+            workers: 0,
+        };
+
+        // Cancelled before it starts, only the first round is played
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let (score, reason) = game_loop_cancellable(hand, RollSource::random(), &cancel);
+        assert_eq!((score, reason), (3, GameOver::Cancelled));
+
+        let cancel = CancelToken::new();
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(std::time::Duration::from_millis(50));
+                cancel.cancel();
+            })
+        };
+        let (score, reason) = game_loop_cancellable(hand, RollSource::random(), &cancel);
+        canceller.join().unwrap();
+        assert_eq!(reason, GameOver::Cancelled);
+        // Every round scores the same 3 points
+        assert!(score > 0 && score % 3 == 0);
+    }
+
+    /// Test why a game ended is reported when it busts or its rolls fail
+    #[test]
+    fn game_over_reasons() {
+        let hand = DiceHand {
+            number_of_dice: 2,
+            number_of_sides: 6,
+            exploding: false,
+            workers: 0,
+        };
+        let cancel = CancelToken::new();
+
+        let rolls = RollSource::scripted(vec![2, 4]);
+        let result = game_loop_cancellable(hand, rolls, &cancel);
+        assert_eq!(result, (6, GameOver::Bust));
+
+        let rolls = RollSource::scripted(vec![3]);
+        let result = game_loop_cancellable(hand, rolls, &cancel);
+        assert_eq!(result, (0, GameOver::RollFailed));
+    }
+
+    /// Test an exploding die adds its rerolls, and only explodes on its top face
+    #[test]
+    fn exploding_dice_add_rerolls() {
+        let hand = DiceHand {
+            number_of_dice: 2,
+            number_of_sides: 6,
+            exploding: true,
+            workers: 0,
+        };
+        // One die rolls 6 then 3 (9, odd), the other 4
+        let rolls = RollSource::scripted(vec![6, 3, 4]);
+        assert_eq!(
+            roll_dice(hand, &rolls),
+            Ok(DiceRollTotal { even: 4, odd: 9 })
+        );
+
+        // Without the rule, a 6 is just a 6
+        let hand = DiceHand {
+            exploding: false,
+            workers: 0,
+            ..hand
+        };
+        let rolls = RollSource::scripted(vec![6, 3]);
+        assert_eq!(
+            roll_dice(hand, &rolls),
+            Ok(DiceRollTotal { even: 6, odd: 3 })
+        );
+    }
+
+    /// Test one sided dice stop exploding at the cap
+    #[test]
+    fn explosions_are_capped() {
+        let hand = DiceHand {
+            number_of_dice: 1,
+            number_of_sides: 1,
+            exploding: true,
+            workers: 0,
+        };
+        let total = RollSource::random().roll_die(hand).unwrap();
+        assert_eq!(total, MAX_EXPLOSIONS as i64 + 1);
+    }
+
+    /// Test a huge hand is rolled in full, on no more threads than there are cores
+    #[test]
+    fn huge_hand_rolled_by_pool() {
+        // Rolled directly, to count the threads started
+        let hand = DiceHand {
+            number_of_dice: 1_000_000,
+            number_of_sides: 1,
+            exploding: false,
+            workers: 0,
+        };
+        let (tx, rx) = mpsc::sync_channel(ROLL_CHANNEL_CAPACITY);
+        let workers = spawn_die(tx, hand, RollSource::random());
+        let cores = thread::available_parallelism().map_or(1, |x| x.get());
+        assert!(workers >= 1 && workers as usize <= cores);
+        assert_eq!(rx.iter().filter(|roll| *roll == Ok(1)).count(), 1_000_000);
+
+        // A small hand doesn't start idle threads
+        let (tx, rx) = mpsc::sync_channel(ROLL_CHANNEL_CAPACITY);
+        assert_eq!(spawn_die(tx, hand.with_dice(1), RollSource::random()), 1);
+        assert_eq!(rx.iter().count(), 1);
+
+        let hand = DiceHand {
+            number_of_dice: 1_000_000,
+            number_of_sides: 1,
+            exploding: false,
+            workers: 0,
+        };
+        let start = std::time::Instant::now();
+        let totals = roll_dice(hand, &RollSource::random()).unwrap();
+        assert_eq!(
+            totals,
+            DiceRollTotal {
+                even: 0,
+                odd: 1_000_000
+            }
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    /// Test a hand's workers cap the threads rolling it, however many dice it holds
+    #[test]
+    fn worker_pool_capped() {
+        let hand = DiceHand::builder()
+            .number_of_dice(200_000)
+            .number_of_sides(6)
+            .workers(3)
+            .build()
+            .unwrap();
+        let rolls = RollSource::random();
+
+        // Count the threads every attempt at rolling the hand starts
+        let started = AtomicI32::new(0);
+        let most_started = AtomicI32::new(0);
+        let spawn = |tx, hand| {
+            let workers = spawn_die(tx, hand, rolls.clone());
+            started.fetch_add(workers, Ordering::Relaxed);
+            most_started.fetch_max(workers, Ordering::Relaxed);
+        };
+        let totals = roll_dice_with(hand, spawn, ROLL_ATTEMPTS).unwrap();
+        assert!(totals.sum() >= 200_000);
+        // Each attempt's threads are done before the next starts
+        assert_eq!(most_started.load(Ordering::Relaxed), 3);
+        assert!(started.load(Ordering::Relaxed) <= 3 * ROLL_ATTEMPTS as i32);
+
+        // The same seeded dice, on any number of threads
+        let hand = hand.with_dice(5_000);
+        let totals = |workers| {
+            let hand = DiceHand { workers, ..hand };
+            roll_dice(hand, &RollSource::seeded(7)).unwrap()
+        };
+        assert_eq!(totals(1), totals(0));
+        assert_eq!(totals(1), totals(16));
+    }
+
+    /// Test rolls over a full (or unbuffered) channel all arrive, and the workers finish
+    #[test]
+    fn bounded_roll_channel_delivers_every_die() {
+        // Every face of a six sided die, many times over: 2 + 4 + 6 even, 1 + 3 + 5 odd
+        let hand = DiceHand::new(6_000, 6).unwrap();
+        let script: Vec<i32> = (1..=6).cycle().take(6_000).collect();
+        assert!(hand.number_of_dice() <= SEQUENTIAL_ROLL_THRESHOLD);
+        assert_eq!(
+            roll_dice(hand, &RollSource::scripted(script.clone())),
+            Ok(DiceRollTotal {
+                even: 12_000,
+                odd: 9_000
+            })
+        );
+
+        // Workers wait on a receiver that isn't reading yet, then hand over each roll directly
+        let (tx, rx) = mpsc::sync_channel(0);
+        let workers = spawn_die(tx, hand, RollSource::scripted(script));
+        assert!(workers >= 1);
+        thread::sleep(Duration::from_millis(20));
+        let rolls: Vec<_> = rx.iter().map(Result::unwrap).collect();
+        assert_eq!(rolls.len(), 6_000);
+        assert_eq!(rolls.iter().sum::<i64>(), 21_000);
+    }
+
+    /// Time a million dice rolled on worker threads against rolled in turn.
+    /// Run with --nocapture to see the timings
+    #[test]
+    fn sequential_and_threaded_rolls_timed() {
+        let hand = DiceHand::new(1_000_000, 6).unwrap();
+        let rolls = RollSource::seeded(11);
+        assert!(hand.number_of_dice > SEQUENTIAL_ROLL_THRESHOLD);
+
+        let start = std::time::Instant::now();
+        let spawn = |tx, hand| {
+            spawn_die(tx, hand, rolls.clone());
+        };
+        let threaded = roll_dice_with(hand, spawn, ROLL_ATTEMPTS).unwrap();
+        let threaded_time = start.elapsed();
+
+        let rolls = RollSource::seeded(11);
+        let start = std::time::Instant::now();
+        let sequential = roll_dice(hand, &rolls).unwrap();
+        let sequential_time = start.elapsed();
+
+        println!("1,000,000 dice: threaded {threaded_time:?}, sequential {sequential_time:?}");
+        assert_eq!(sequential, threaded);
+        assert!(sequential_time < std::time::Duration::from_secs(10));
+    }
+
+    /// Test an unanimated single roll prints just the face it returns
+    #[test]
+    fn single_roll_without_animation() {
+        for _ in 0..100 {
+            let mut output = Vec::new();
+            let value = roll_single_die(&mut output, 6, false).unwrap();
+            assert!((1..=6).contains(&value));
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!("You rolled a {value} on a 6-sided die\n")
+            );
+        }
+    }
+
+    /// Test the chi-square statistic against hand worked values
+    #[test]
+    fn chi_square_of_known_counts() {
+        assert_eq!(chi_square(&[10, 10, 10, 10, 10, 10]), 0.0);
+        // expected 10 each: (4 + 4 + 0 + 0 + 1 + 1) / 10
+        assert!((chi_square(&[12, 8, 10, 10, 11, 9]) - 1.0).abs() < 1e-9);
+        assert_eq!(chi_square(&[0, 0, 0]), 0.0);
+
+        // Tabulated critical values: 11.07 for 5 degrees of freedom at 0.05, 16.81 for 6 at 0.01
+        assert!((chi_square_critical_value(5, 1.644_854) - 11.07).abs() < 0.05);
+        assert!((chi_square_critical_value(6, 2.326_348) - 16.81).abs() < 0.05);
+    }
+
+    /// Test the selftest passes an even die and fails a loaded one
+    #[test]
+    fn self_test_catches_loaded_dice() {
+        // Cycles through the faces, so every face comes up equally often
+        let mut face = 0;
+        let even = || {
+            face = face % 6 + 1;
+            Ok(face)
+        };
+        let result = self_test(6, 6_000, 1.644_854, even).unwrap().unwrap();
+        assert_eq!(result.statistic, 0.0);
+        assert!(result.passed());
+
+        // Comes up six a third of the time, rather than a sixth
+        let mut rolls = 0;
+        let loaded = || {
+            rolls += 1;
+            Ok(if rolls % 3 == 0 {
+                6
+            } else {
+                (rolls % 5 + 1) as i8
+            })
+        };
+        let result = self_test(6, 6_000, 3.090_232, loaded).unwrap().unwrap();
+        assert!(!result.passed(), "{result:?}");
+
+        // An off-by-one die is caught before counting
+        assert!(matches!(
+            self_test(6, 10, 1.644_854, || Ok(7)),
+            Err(DiceError::ValueOutOfRange(7))
+        ));
+        assert!(self_test(1, 10, 1.644_854, || Ok(1)).unwrap().is_none());
+    }
+
+    /// Test the starting hand is read from the command line, with the defaults kept
+    #[test]
+    fn hand_from_args() {
+        let args = |line: &str| {
+            line.split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        let parse_hand_args = |line| {
+            parse_args(args(line), Settings::default()).map(|settings| settings.starting_hand)
+        };
+
+        assert_eq!(parse_hand_args(""), Ok(STARTING_HAND));
+        let hand = parse_hand_args("--dice 20 --sides 6").unwrap();
+        assert_eq!((hand.number_of_dice, hand.number_of_sides), (20, 6));
+        let hand = parse_hand_args("--sides 2").unwrap();
+        assert_eq!((hand.number_of_dice, hand.number_of_sides), (12, 2));
+
+        assert_eq!(
+            parse_hand_args("--dice 0"),
+            Err("a hand needs at least 1 die, not 0".to_string())
+        );
+        assert_eq!(
+            parse_hand_args("--dice -3"),
+            Err("a hand needs at least 1 die, not -3".to_string())
+        );
+        assert_eq!(
+            parse_hand_args("--sides 1"),
+            Err("dice need at least 2 sides, not 1".to_string())
+        );
+        assert_eq!(
+            parse_hand_args("--sides 300"),
+            Err("--sides needs a whole number in range, not '300'".to_string())
+        );
+        assert_eq!(
+            parse_hand_args("--dice"),
+            Err("--dice needs a number".to_string())
+        );
+        assert_eq!(
+            parse_hand_args("--exploding"),
+            Err("unknown option '--exploding'".to_string())
+        );
+    }
+
+    /// Test seeded dice and games replay exactly
+    #[test]
+    fn seeded_games_replay() {
+        let roll_all = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..100)
+                .map(|_| Dice::new_with_rng(&mut rng, 6).unwrap().value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(roll_all(42), roll_all(42));
+        assert!(roll_all(42).iter().all(|value| (1..=6).contains(value)));
+
+        // A large enough hand for a few rounds, rolled across several threads
+        let hand = DiceHand {
+            number_of_dice: 5_000,
+            number_of_sides: 6,
+            exploding: true,
+            workers: 0,
+        };
+        for seed in [0, 1, u64::MAX] {
+            assert_eq!(
+                game_loop_with_rolls(hand, RollSource::seeded(seed)),
+                game_loop_with_rolls(hand, RollSource::seeded(seed))
+            );
+        }
+    }
+
+    /// Test a config's settings replace the defaults, and invalid values name their field
+    #[test]
+    fn settings_from_config() {
+        let settings = Settings::from_config(Config::default()).unwrap();
+        assert_eq!(settings.starting_hand, STARTING_HAND);
+        assert_eq!(settings.score_file_path, SCORE_FILE_PATH);
+        assert_eq!(settings.top_scores, TOP_SCORES);
+
+        let config =
+            Config::parse("dice = 20\nsides = 6\nscore_file = \"a.msgpack\"\ntop_scores = 5");
+        let settings = Settings::from_config(config.unwrap()).unwrap();
+        assert_eq!(
+            (
+                settings.starting_hand.number_of_dice,
+                settings.starting_hand.number_of_sides
+            ),
+            (20, 6)
+        );
+        assert_eq!(settings.score_file_path, "a.msgpack");
+        assert_eq!(settings.top_scores, 5);
+        assert_eq!(settings.starting_hand.workers, 0);
+        let config = Config::parse("workers = 3").unwrap();
+        assert_eq!(
+            Settings::from_config(config).unwrap().starting_hand.workers,
+            3
+        );
+
+        // The command line still has the last word on the hand
+        let settings = parse_args(vec!["--dice".to_string(), "3".to_string()], settings);
+        assert_eq!(
+            settings.map(|settings| {
+                let hand = settings.starting_hand;
+                (hand.number_of_dice, hand.number_of_sides)
+            }),
+            Ok((3, 6))
+        );
+
+        let invalid = |text| {
+            Settings::from_config(Config::parse(text).unwrap())
+                .err()
+                .unwrap()
+        };
+        assert_eq!(
+            invalid("dice = 0"),
+            "dice: a hand needs at least 1 die, not 0"
+        );
+        assert_eq!(
+            invalid("sides = 1"),
+            "sides: dice need at least 2 sides, not 1"
+        );
+        assert_eq!(
+            invalid("top_scores = 0"),
+            "top_scores: at least 1 score must be kept"
+        );
+    }
+
+    /// Test each profile keeps its own scores file, and profiles are listed from their files
+    #[test]
+    fn profiles_keep_separate_scores() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+        let profile = |name: &str| Settings {
+            profile: Some(name.to_string()),
+            ..settings.clone()
+        };
+        assert_eq!(settings.score_file(), settings.score_file_path);
+        assert!(profile("alice")
+            .score_file()
+            .ends_with("scores_alice.msgpack"));
+
+        // One game as alice, two as bob, then back to the shared scores for one more
+        let mut input = io::Cursor::new(
+            "profile alice\nstart\n\nprofile bob\nstart\n\nstart\n\nprofile default\nstart\n\nexit\n",
+        );
+        play_session(&mut input, &settings).unwrap();
+        let alice_scores = read_state_from_file(&profile("alice").score_file());
+        assert_eq!(alice_scores.len(), 1);
+        // Unnamed scores are the profile's
+        assert_eq!(alice_scores[0].name, "alice");
+        assert_eq!(read_state_from_file(&profile("bob").score_file()).len(), 2);
+        assert_eq!(read_state_from_file(&settings.score_file()).len(), 1);
+
+        // Files that aren't profiles' scores aren't listed
+        std::fs::write(directory.path().join("scores_not a name.msgpack"), "").unwrap();
+        std::fs::write(directory.path().join("other_carol.msgpack"), "").unwrap();
+        assert_eq!(settings.list_profiles(), vec!["alice", "bob"]);
+
+        assert!(is_profile_name("player-2_b"));
+        assert!(!is_profile_name(""));
+        assert!(!is_profile_name("../alice"));
+    }
+
+    /// Test a failed save is returned to the caller
+    #[test]
+    fn failed_save_is_an_error() {
+        let directory = tempfile::tempdir().unwrap();
+        let directory_path = directory.path().to_str().unwrap();
+        let scores = [ScoreEntry::from(1), ScoreEntry::from(2)];
+        assert!(save_state_to_file(directory_path, &scores).is_err());
+
+        let file_path = directory.path().join("scores.msgpack");
+        assert!(save_state_to_file(file_path.to_str().unwrap(), &scores).is_ok());
+        assert_eq!(read_state_from_file(file_path.to_str().unwrap()), scores);
+    }
+
+    /// Test scores keep their metadata, and files of bare scores still load
+    #[test]
+    fn score_entries_saved_with_metadata() {
+        let directory = tempfile::tempdir().unwrap();
+        let file_path = directory.path().join("scores.msgpack");
+        let file_path = file_path.to_str().unwrap();
+
+        let entry = ScoreEntry {
+            score: 345,
+            achieved_at: 1_700_000_000,
+            dice: 12,
+            sides: 7,
+            name: String::new(),
+        };
+        save_state_to_file(file_path, &[entry.clone(), ScoreEntry::from(20)]).unwrap();
+        let scores = read_state_from_file(file_path);
+        assert_eq!(scores.last(), Some(&entry));
+        assert_eq!(scores.first(), Some(&ScoreEntry::from(20)));
+
+        // Written as a plain Vec<i64>, before scores kept metadata
+        let legacy: Vec<i64> = vec![50, 30, 20];
+        std::fs::write(file_path, rmp_serde::to_vec(&legacy).unwrap()).unwrap();
+        let scores = read_state_from_file(file_path);
+        assert_eq!(
+            scores,
+            vec![
+                ScoreEntry::from(20),
+                ScoreEntry::from(30),
+                ScoreEntry::from(50)
+            ]
+        );
+
+        assert_eq!(entry.to_string(), "345  (2023-11-14, 12 7-sided dice)");
+        assert_eq!(ScoreEntry::from(20).to_string(), "20");
+    }
+
+    /// Test finished games are named by the player, and entries saved before names still load
+    #[test]
+    fn scores_named_by_player() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+
+        // A blank name is anonymous, and the input running dry too
+        let mut input = io::Cursor::new("start\n  Ann Lee \nstart\n\nstart\n");
+        play_session(&mut input, &settings).unwrap();
+        let mut names: Vec<_> = read_state_from_file(&settings.score_file_path)
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["", "", "Ann Lee"]);
+
+        let mut input = io::Cursor::new("a\tvery long name, longer than a name is kept\n");
+        let name = read_player_name(&mut input, "").unwrap();
+        assert_eq!(name, "avery long name, longer ");
+
+        let entry = ScoreEntry {
+            name: "Ann".to_string(),
+            ..ScoreEntry::from(345)
+        };
+        assert_eq!(entry.to_string(), "345 by Ann");
+
+        // Entries written before they were named
+        let file_path = directory.path().join("unnamed.msgpack");
+        let file_path = file_path.to_str().unwrap();
+        let unnamed = vec![(345_i64, 1_700_000_000_u64, 12_i32, 7_u32)];
+        std::fs::write(file_path, rmp_serde::to_vec(&unnamed).unwrap()).unwrap();
+        let scores = read_state_from_file(file_path);
+        assert_eq!(scores.len(), 1);
+        assert_eq!((scores[0].score, scores[0].name.as_str()), (345, ""));
+    }
+
+    /// Test the statistics of odd and even numbers of scores, and of none
+    #[test]
+    fn score_statistics_median() {
+        let stats = score_statistics(&[30, 10, 50]);
+        assert_eq!(stats.games, 3);
+        assert_eq!(stats.median, 30.0);
+        assert_eq!(stats.mean, 30.0);
+        assert_eq!((stats.min, stats.max), (10, 50));
+
+        let stats = score_statistics(&[40, 10, 25, 20]);
+        assert_eq!(stats.median, 22.5);
+        assert_eq!(stats.mean, 23.75);
+        assert_eq!((stats.min, stats.max), (10, 40));
+
+        assert_eq!(score_statistics(&[7]).median, 7.0);
+        assert_eq!(
+            score_statistics(&[i64::MAX, i64::MAX]).mean,
+            i64::MAX as f64
+        );
+        assert_eq!(score_statistics(&[]), ScoreStats::default());
+    }
+
+    /// Test every logged game of the active profile is summarised, and the summary's text
+    #[test]
+    fn game_statistics_from_log() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+        assert!(read_game_log(&settings.game_log_path).is_empty());
+
+        // More games than scores are kept, in two profiles
+        let settings = Settings {
+            top_scores: 1,
+            ..settings
+        };
+        let mut input =
+            io::Cursor::new("start\n\nstart\n\nstart\n\nprofile alice\nstart\n\nstats\nexit\n");
+        play_session(&mut input, &settings).unwrap();
+        let games = read_game_log(&settings.game_log_path);
+        assert_eq!(games.len(), 4);
+        let shared: Vec<_> = games
+            .iter()
+            .filter(|game| game.profile.is_none())
+            .cloned()
+            .collect();
+        assert_eq!(game_statistics(&shared).scores.games, 3);
+
+        // A line cut short is skipped, older lines without a profile are the shared games
+        let mut log = std::fs::read_to_string(&settings.game_log_path).unwrap();
+        log.push_str("{\"dice\":12,\"sides\":7,\"score\":40,\"rounds\":2,\"timestamp\":1}\n");
+        log.push_str("{\"dice\":12,\"sid");
+        std::fs::write(&settings.game_log_path, log).unwrap();
+        let games = read_game_log(&settings.game_log_path);
+        assert_eq!(games.len(), 5);
+        assert_eq!(games[4].profile, None);
+
+        let game = |score, rounds| GameLogEntry {
+            dice: 12,
+            sides: 7,
+            score,
+            rounds,
+            timestamp: 0,
+            profile: None,
+        };
+        let stats = game_statistics(&[game(1200, 3), game(40, 1), game(300, 2), game(95, 4)]);
+        assert_eq!(stats.mean_rounds, 2.5);
+        let mut output = Vec::new();
+        write_game_statistics(&mut output, &stats).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                "\nGame Statistics:\n",
+                "  Games played:  4\n",
+                "  Mean score:    408.8\n",
+                "  Median score:  197.5\n",
+                "  Best score:    1,200\n",
+                "  Worst score:   40\n",
+                "  Mean rounds:   2.5\n\n"
+            )
+        );
+
+        let mut output = Vec::new();
+        write_game_statistics(&mut output, &game_statistics(&[])).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\nNo games played yet\n\n"
+        );
+    }
+
+    /// Test dates are converted from the epoch across leap days and centuries
+    #[test]
+    fn dates_from_timestamps() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(86_399), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(951_868_800), "2000-03-01");
+        assert_eq!(format_date(1_709_164_800), "2024-02-29");
+        assert_eq!(format_date(4_107_542_400), "2100-03-01");
+    }
+
+    /// Test a save cut short (leaving garbage in the temporary file) keeps the old scores,
+    /// and the next save replaces both
+    #[test]
+    fn interrupted_save_keeps_scores() {
+        let directory = tempfile::tempdir().unwrap();
+        let file_path = directory.path().join("scores.msgpack");
+        let file_path = file_path.to_str().unwrap();
+        let temporary_path = format!("{file_path}.tmp");
+
+        save_state_to_file(file_path, &[ScoreEntry::from(40)]).unwrap();
+        assert!(!Path::new(&temporary_path).exists());
+
+        std::fs::write(&temporary_path, [0xc1, 0x93, 0x01]).unwrap();
+        assert_eq!(read_state_from_file(file_path), vec![ScoreEntry::from(40)]);
+
+        save_state_to_file(file_path, &[ScoreEntry::from(50)]).unwrap();
+        assert_eq!(read_state_from_file(file_path), vec![ScoreEntry::from(50)]);
+        assert!(!Path::new(&temporary_path).exists());
+    }
+
+    /// Test scores round trip between MessagePack and JSON files, whichever is read
+    #[test]
+    fn scores_round_trip_through_json() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = |file_name| {
+            directory
+                .path()
+                .join(file_name)
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        let scores = vec![
+            ScoreEntry::from(20),
+            ScoreEntry {
+                score: 345,
+                achieved_at: 1_700_000_000,
+                dice: 12,
+                sides: 7,
+                name: "Ann".to_string(),
+            },
+        ];
+
+        save_state_to_file(&path("scores.msgpack"), &scores).unwrap();
+        let from_msgpack = read_state_from_file(&path("scores.msgpack"));
+        save_state_to_file(&path("scores.json"), &from_msgpack).unwrap();
+        let json = std::fs::read_to_string(path("scores.json")).unwrap();
+        assert!(json.contains("\"achieved_at\": 1700000000"), "{json}");
+        let from_json = read_state_from_file(&path("scores.json"));
+        save_state_to_file(&path("again.msgpack"), &from_json).unwrap();
+        assert_eq!(read_state_from_file(&path("again.msgpack")), scores);
+
+        // Hand written JSON, of bare scores, is read whatever the file is called
+        std::fs::write(path("edited.msgpack"), "[10, 30]").unwrap();
+        assert_eq!(
+            read_state_from_file(&path("edited.msgpack")),
+            vec![ScoreEntry::from(10), ScoreEntry::from(30)]
+        );
+    }
+
+    /// Test a file that is neither format starts empty, kept aside rather than overwritten,
+    /// and its scores can be restored once it's repaired
+    #[test]
+    fn corrupt_scores_kept_aside() {
+        let directory = tempfile::tempdir().unwrap();
+        let file_path = directory.path().join("scores.json");
+        let file_path = file_path.to_str().unwrap();
+        std::fs::write(file_path, "[10, 30").unwrap();
+
+        assert!(read_state_from_file(file_path).is_empty());
+        assert!(!Path::new(file_path).exists());
+        let backups: Vec<_> = std::fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let backup_path = backups[0].to_str().unwrap();
+        assert!(backup_path.starts_with(&format!("{file_path}.corrupt-")));
+        assert_eq!(std::fs::read_to_string(backup_path).unwrap(), "[10, 30");
+
+        // Still broken, nothing is restored
+        let mut scores = vec![ScoreEntry::from(20)];
+        let error = restore_scores(backup_path, &mut scores).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(scores, vec![ScoreEntry::from(20)]);
+
+        std::fs::write(backup_path, "[10, 30]").unwrap();
+        assert_eq!(restore_scores(backup_path, &mut scores).unwrap(), 2);
+        let values: Vec<_> = scores.iter().map(|entry| entry.score).collect();
+        assert_eq!(values, vec![10, 20, 30]);
+    }
+
+    /// Test a missing score file quietly starts empty
+    #[test]
+    fn missing_scores_start_empty() {
+        let directory = tempfile::tempdir().unwrap();
+        let file_path = directory.path().join("scores.msgpack");
+        assert!(read_state_from_file(file_path.to_str().unwrap()).is_empty());
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 0);
+    }
+
+    /// Test repeated scores are all kept and listed, in order
+    #[test]
+    fn repeated_scores_kept() {
+        let mut scores = Vec::new();
+        for score in [30, 50, 30, 10, 50] {
+            insert_score(&mut scores, ScoreEntry::from(score));
+        }
+        let values: Vec<_> = scores.iter().map(|entry| entry.score).collect();
+        assert_eq!(values, vec![10, 30, 30, 50, 50]);
+
+        // The same game twice, down to the second it finished, is still two games
+        let entry = ScoreEntry {
+            score: 42,
+            achieved_at: 1_700_000_000,
+            dice: 12,
+            sides: 7,
+            name: "Ann".to_string(),
+        };
+        insert_score(&mut scores, entry.clone());
+        insert_score(&mut scores, entry.clone());
+        assert_eq!(scores.iter().filter(|kept| **kept == entry).count(), 2);
+
+        let directory = tempfile::tempdir().unwrap();
+        let file_path = directory.path().join("scores.msgpack");
+        let file_path = file_path.to_str().unwrap();
+        save_state_to_file(file_path, &scores).unwrap();
+        assert_eq!(read_state_from_file(file_path), scores);
+    }
+
+    /// Test --async selects the tokio game loop, only when it's built in
+    #[test]
+    fn async_flag_needs_tokio_feature() {
+        let parsed = parse_args(vec!["--async".to_string()], Settings::default());
+        match parsed {
+            Ok(settings) => assert!(cfg!(feature = "tokio") && settings.async_game),
+            Err(error) => assert!(!cfg!(feature = "tokio") && error.contains("tokio feature")),
+        }
+        assert!(
+            !parse_args(Vec::new(), Settings::default())
+                .unwrap()
+                .async_game
+        );
+    }
+
+    /// Test a seed from the command line or the menu replays the same game
+    #[test]
+    fn seeded_session_replays_games() {
+        let args = vec!["--seed".to_string(), "1234".to_string()];
+        assert_eq!(
+            parse_args(args, Settings::default()).unwrap().seed,
+            Some(1234)
+        );
+        let args = vec!["--seed".to_string(), "-1".to_string()];
+        assert!(parse_args(args, Settings::default()).is_err());
+
+        let directory = tempfile::tempdir().unwrap();
+        let settings = test_settings(directory.path());
+        let mut input = io::Cursor::new("seed 99\nstart\n\nstart\n\nseed random\nexit\n");
+        play_session(&mut input, &settings).unwrap();
+        let scores = read_state_from_file(&settings.score_file_path);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].score, scores[1].score);
+        assert_eq!(
+            scores[0].score,
+            game_loop_with_rolls(STARTING_HAND, RollSource::seeded(99))
+        );
+    }
+}
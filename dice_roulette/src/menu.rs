@@ -0,0 +1,360 @@
+//! Menu commands. A single table describes every command, the parser and the help text
+//! are both derived from it so they can never disagree on what is accepted.
+
+use std::io::{self, Write};
+
+/// Actions the menu loop can take
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Command {
+    Start,
+    Rules,
+    Scores,
+    Stats,
+    Replay,
+    Profile,
+    Seed,
+    Restore,
+    Roll,
+    SelfTest,
+    Config,
+    Help,
+    Exit,
+}
+
+/// Description of a menu command
+pub(crate) struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// One line description, listed by "help"
+    pub summary: &'static str,
+    /// Arguments, details and examples, printed by "help <command>"
+    pub usage: &'static str,
+    /// What the menu loop does when the command is entered
+    pub command: Command,
+}
+
+/// Every menu command, in the order they are listed
+pub(crate) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "start",
+        aliases: &["play"],
+        summary: "Start a new game",
+        usage: concat!(
+            "start\n",
+            "Rolls the starting hand and plays rounds until the even total beats the odd total. ",
+            "The final score is added to the scores table, under the name you give ",
+            "(the profile's name, or anonymous, if left blank).\n",
+            "Example: start"
+        ),
+        command: Command::Start,
+    },
+    CommandSpec {
+        name: "rules",
+        aliases: &[],
+        summary: "Explain how the game is played",
+        usage: concat!(
+            "rules\n",
+            "Prints the rules along with the starting hand used for new games.\n",
+            "Example: rules"
+        ),
+        command: Command::Rules,
+    },
+    CommandSpec {
+        name: "scores",
+        aliases: &["top"],
+        summary: "Show the top 10 scores",
+        usage: concat!(
+            "scores\n",
+            "Lists the best scores recorded so far, highest first.\n",
+            "Example: scores"
+        ),
+        command: Command::Scores,
+    },
+    CommandSpec {
+        name: "stats",
+        aliases: &["statistics"],
+        summary: "Summarise every game played",
+        usage: concat!(
+            "stats\n",
+            "Prints how many games the active profile has played (as kept in the game log, ",
+            "not only the top scores), their mean, median, best and worst score, ",
+            "and how many rounds a game lasts on average.\n",
+            "Example: stats"
+        ),
+        command: Command::Stats,
+    },
+    CommandSpec {
+        name: "replay",
+        aliases: &["last"],
+        summary: "Go over the last game round by round",
+        usage: concat!(
+            "replay\n",
+            "Prints each round of the last game played: the dice rolled, their even and odd ",
+            "totals and the running score, along with the seed to play it again.\n",
+            "Example: replay"
+        ),
+        command: Command::Replay,
+    },
+    CommandSpec {
+        name: "profile",
+        aliases: &["profiles"],
+        summary: "Show, list or switch player profiles",
+        usage: concat!(
+            "profile [name]\n",
+            "Each profile keeps its own scores. Without a name, shows the active profile ",
+            "and lists the existing ones. With a name (letters, digits, - and _), switches ",
+            "to that profile, creating it with its first score. ",
+            "'profile default' goes back to the shared scores.\n",
+            "Example: profile alice"
+        ),
+        command: Command::Profile,
+    },
+    CommandSpec {
+        name: "seed",
+        aliases: &[],
+        summary: "Replay games from a seed",
+        usage: concat!(
+            "seed [number|random]\n",
+            "Every game prints the seed its dice were rolled from. Setting that seed plays ",
+            "the same game again (with the same starting hand), until 'seed random' goes back ",
+            "to a new seed for each game. Without an argument, shows the seed in use.\n",
+            "Example: seed 1234"
+        ),
+        command: Command::Seed,
+    },
+    CommandSpec {
+        name: "restore",
+        aliases: &[],
+        summary: "Add back the scores of a repaired score file",
+        usage: concat!(
+            "restore <file>\n",
+            "A score file that can't be read is moved aside (to <file>.corrupt-<time>) ",
+            "and play starts with no scores. Once repaired, restore adds its scores ",
+            "to the active profile's.\n",
+            "Example: restore scores.msgpack.corrupt-1700000000"
+        ),
+        command: Command::Restore,
+    },
+    CommandSpec {
+        name: "roll",
+        aliases: &[],
+        summary: "Roll a single die, just for fun",
+        usage: concat!(
+            "roll [--no-animation]\n",
+            "Rolls one die of the starting hand, spinning through faces before it settles ",
+            "(unless --no-animation is given, or the output isn't a terminal). ",
+            "Doesn't count towards the scores.\n",
+            "Example: roll --no-animation"
+        ),
+        command: Command::Roll,
+    },
+    CommandSpec {
+        name: "selftest",
+        aliases: &[],
+        summary: "Check the dice are fair",
+        usage: concat!(
+            "selftest [significance]\n",
+            "Rolls one die of the starting hand many times and checks the faces come up ",
+            "evenly (a chi-square test). The significance is 0.1, 0.05 (the default), ",
+            "0.01 or 0.001: the chance of a fair die failing.\n",
+            "Example: selftest 0.01"
+        ),
+        command: Command::SelfTest,
+    },
+    CommandSpec {
+        name: "config",
+        aliases: &["settings"],
+        summary: "Show the current game configuration",
+        usage: concat!(
+            "config\n",
+            "Prints the starting hand, scoring rule, hand cap, ",
+            "score and log file locations, and how dice are rolled.\n",
+            "Example: config"
+        ),
+        command: Command::Config,
+    },
+    CommandSpec {
+        name: "help",
+        aliases: &["?"],
+        summary: "List commands, or explain one in detail",
+        usage: concat!(
+            "help [command]\n",
+            "Without an argument lists every command. ",
+            "With a command name (or alias) prints its usage and examples.\n",
+            "Examples: help, help start"
+        ),
+        command: Command::Help,
+    },
+    CommandSpec {
+        name: "exit",
+        aliases: &["quit", "q"],
+        summary: "Leave the game",
+        usage: concat!(
+            "exit\n",
+            "Ends the session. Scores are already saved after every game.\n",
+            "Example: exit"
+        ),
+        command: Command::Exit,
+    },
+];
+
+/// Find the command entered by name or alias
+pub(crate) fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS
+        .iter()
+        .find(|spec| spec.name == name || spec.aliases.contains(&name))
+}
+
+/// Split a line of input into its command and the (possibly empty) argument that follows
+pub(crate) fn parse_command(input: &str) -> Option<(Command, &str)> {
+    let (name, argument) = input.split_once(' ').unwrap_or((input, ""));
+    find_command(name).map(|spec| (spec.command, argument.trim()))
+}
+
+pub(crate) fn print_menu() {
+    let names: Vec<_> = COMMANDS.iter().map(|spec| capitalise(spec.name)).collect();
+    println!("Dice Factions!");
+    println!("Please enter an action from the follow list:");
+    println!("{}:", names.join(", "));
+}
+
+/// Write the command list, or the detailed usage of topic when given
+pub(crate) fn write_help<W: Write>(out: &mut W, topic: &str) -> io::Result<()> {
+    // Begin and end with a new line to form isolated paragraph
+    writeln!(out)?;
+    if topic.is_empty() {
+        writeln!(out, "Commands:")?;
+        for spec in COMMANDS {
+            writeln!(out, "  {:<8}{}", spec.name, spec.summary)?;
+        }
+        writeln!(out, "Enter \"help <command>\" for details.")?;
+    } else if let Some(spec) = find_command(topic) {
+        writeln!(out, "{}: {}", spec.name, spec.summary)?;
+        if !spec.aliases.is_empty() {
+            writeln!(out, "Aliases: {}", spec.aliases.join(", "))?;
+        }
+        writeln!(out, "Usage: {}", spec.usage)?;
+    } else {
+        writeln!(
+            out,
+            "Unknown command \"{topic}\". Did you mean \"{}\"?",
+            closest_command(topic).name
+        )?;
+    }
+    writeln!(out)
+}
+
+/// The command whose name or alias is the fewest edits away from name
+fn closest_command(name: &str) -> &'static CommandSpec {
+    COMMANDS
+        .iter()
+        .min_by_key(|spec| {
+            std::iter::once(spec.name)
+                .chain(spec.aliases.iter().copied())
+                .map(|candidate| edit_distance(name, candidate))
+                .min()
+                .unwrap_or(usize::MAX)
+        })
+        .expect("The command table is never empty")
+}
+
+/// Levenshtein distance between two strings (in characters)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the processed prefix of a to each prefix of b
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test every command is registered exactly once, with help text
+    #[test]
+    fn every_command_has_help() {
+        for command in [
+            Command::Start,
+            Command::Rules,
+            Command::Scores,
+            Command::Stats,
+            Command::Replay,
+            Command::Profile,
+            Command::Seed,
+            Command::Restore,
+            Command::Roll,
+            Command::SelfTest,
+            Command::Config,
+            Command::Help,
+            Command::Exit,
+        ] {
+            let specs: Vec<_> = COMMANDS
+                .iter()
+                .filter(|spec| spec.command == command)
+                .collect();
+            assert_eq!(specs.len(), 1, "{command:?} must be registered once");
+            assert!(!specs[0].summary.is_empty());
+            assert!(!specs[0].usage.is_empty());
+        }
+    }
+
+    /// Test the parser accepts exactly the names and aliases help describes
+    #[test]
+    fn parser_and_help_share_entries() {
+        for spec in COMMANDS {
+            for name in std::iter::once(spec.name).chain(spec.aliases.iter().copied()) {
+                assert_eq!(parse_command(name), Some((spec.command, "")));
+
+                let mut help = Vec::new();
+                write_help(&mut help, name).unwrap();
+                let help = String::from_utf8(help).unwrap();
+                assert!(help.contains(spec.summary));
+            }
+        }
+        assert_eq!(parse_command("unknown"), None);
+    }
+
+    /// Test help arguments are passed through
+    #[test]
+    fn parse_command_argument() {
+        assert_eq!(parse_command("help start"), Some((Command::Help, "start")));
+        assert_eq!(parse_command("help"), Some((Command::Help, "")));
+    }
+
+    /// Test unknown help topics suggest the closest command
+    #[test]
+    fn unknown_help_suggests_closest() {
+        assert_eq!(closest_command("strat").name, "start");
+        assert_eq!(closest_command("score").name, "scores");
+        assert_eq!(closest_command("quite").name, "exit");
+
+        let mut help = Vec::new();
+        write_help(&mut help, "rulez").unwrap();
+        let help = String::from_utf8(help).unwrap();
+        assert!(help.contains("Did you mean \"rules\"?"));
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", "exit"), 4);
+        assert_eq!(edit_distance("start", "start"), 0);
+        assert_eq!(edit_distance("strat", "start"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,78 @@
+use dice_roulette::{
+    game_loop_with_rolls, DiceHand, DiceRollTotal, GameState, RollSource, RoundOutcome,
+};
+
+fn hand(number_of_dice: i32, number_of_sides: i8) -> DiceHand {
+    DiceHand::builder()
+        .number_of_dice(number_of_dice)
+        .number_of_sides(number_of_sides)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn scripted_game_stepped_to_the_end() {
+    // 3 + 5 odd beat 2 even by 6, then six dice roll an even lead
+    let rolls = RollSource::scripted(vec![3, 5, 2, 2, 4, 6, 1, 1, 3]);
+    let mut game = GameState::with_rolls(hand(3, 6), rolls);
+
+    assert_eq!(
+        game.step(),
+        RoundOutcome::Rolled {
+            round: 1,
+            totals: DiceRollTotal { even: 2, odd: 8 },
+            next_hand: hand(6, 6),
+        }
+    );
+    assert_eq!(game.hand(), Some(hand(6, 6)));
+    assert_eq!(
+        game.step(),
+        RoundOutcome::Bust {
+            round: 2,
+            totals: DiceRollTotal { even: 12, odd: 5 },
+        }
+    );
+    assert!(game.is_over());
+    assert_eq!(game.score(), 27);
+    assert_eq!(game.rounds(), 2);
+
+    // Stepping a finished game changes nothing
+    assert_eq!(game.step(), RoundOutcome::Finished);
+    assert_eq!(game.score(), 27);
+}
+
+#[test]
+fn stepped_game_matches_game_loop() {
+    // The same seeded dice, rolled in turn or across threads, score the same game
+    for seed in 0..20 {
+        let mut game = GameState::with_rolls(hand(12, 7), RollSource::seeded(seed));
+        while !game.is_over() {
+            assert!(!matches!(
+                game.step(),
+                RoundOutcome::RollFailed(_) | RoundOutcome::Finished
+            ));
+        }
+        assert_eq!(
+            game.score(),
+            game_loop_with_rolls(hand(12, 7), RollSource::seeded(seed))
+        );
+    }
+}
+
+#[test]
+fn random_game_ends() {
+    let mut game = GameState::new(hand(12, 7));
+    while !game.is_over() {
+        game.step();
+    }
+    assert!(game.score() >= 12);
+    assert!(game.rounds() >= 1);
+}
+
+#[test]
+fn failed_roll_ends_game() {
+    let mut game = GameState::with_rolls(hand(2, 6), RollSource::scripted(vec![1]));
+    assert!(matches!(game.step(), RoundOutcome::RollFailed(_)));
+    assert!(game.is_over());
+    assert_eq!(game.score(), 0);
+}
@@ -1,7 +1,87 @@
 // 2023 Hayden Sip
 
-use std::{path::{PathBuf}, ffi::OsStr};
-use walkdir::{WalkDir};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::UNIX_EPOCH,
+};
+use glob::Pattern;
+use regex::Regex;
+use walkdir::{DirEntry, WalkDir};
+
+/// Options refining how the directory tree is walked
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    /// Don't descend into subdirectories containing their own `.git` (submodules, vendored repos)
+    pub stop_at_nested_git: bool,
+    /// Don't descend into other filesystems mounted below the root (like `find -xdev`).
+    /// Filesystems are told apart by device id, so this has no effect outside Unix
+    pub same_filesystem: bool,
+}
+
+/// A search pattern which failed to compile
+#[derive(Debug)]
+pub enum PatternError {
+    Glob(glob::PatternError),
+    Regex(regex::Error),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Glob(error) => write!(f, "invalid glob: {error}"),
+            PatternError::Regex(error) => write!(f, "invalid regex: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<glob::PatternError> for PatternError {
+    fn from(error: glob::PatternError) -> Self {
+        PatternError::Glob(error)
+    }
+}
+
+impl From<regex::Error> for PatternError {
+    fn from(error: regex::Error) -> Self {
+        PatternError::Regex(error)
+    }
+}
+
+/// Where a file stands in its git repository
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitStatus {
+    /// Changed in the working tree (whether or not other changes are staged)
+    Modified,
+    /// Changes staged for the next commit, and none besides
+    Staged,
+    Untracked,
+    /// In the repository, without changes
+    Clean,
+    /// Not inside a git repository (or git couldn't be run)
+    NotInRepository,
+}
+
+impl fmt::Display for GitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            GitStatus::Modified => "modified",
+            GitStatus::Staged => "staged",
+            GitStatus::Untracked => "untracked",
+            GitStatus::Clean => "clean",
+            GitStatus::NotInRepository => "n/a",
+        };
+        f.write_str(status)
+    }
+}
 
 fn os_str_contains_name(os_file_name : &OsStr, file_name : &str) -> bool {
     os_file_name
@@ -10,20 +90,503 @@ fn os_str_contains_name(os_file_name : &OsStr, file_name : &str) -> bool {
         .contains(file_name)
 }
 
+// Byte ranges of every (non-overlapping) occurrence of file_name within name
+pub fn match_ranges(name : &str, file_name : &str) -> Vec<Range<usize>> {
+    if file_name.is_empty() {
+        return Vec::new();
+    }
+    name.match_indices(file_name)
+        .map(|(start, matched)| start..start + matched.len())
+        .collect()
+}
+
+// A directory below the search root which is the top of another git repository
+fn is_nested_git_root(entry : &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.path().join(".git").exists()
+}
+
+// Id of the device holding path (None if unknown, or not on Unix)
+#[cfg(unix)]
+fn device_id(metadata : &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_metadata : &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+// An entry on a different device than the root. Unknown devices are never different
+fn on_other_device(entry_device : Option<u64>, root_device : Option<u64>) -> bool {
+    matches!((entry_device, root_device), (Some(entry), Some(root)) if entry != root)
+}
+
+// Walk every readable entry under root_directory (sorted by name, so results are deterministic)
+fn walk(root_directory: &str, options : SearchOptions) -> impl Iterator<Item = DirEntry> {
+    let root_device = options
+        .same_filesystem
+        .then(|| Path::new(root_directory).metadata().ok())
+        .flatten()
+        .and_then(|x| device_id(&x));
+    let other_filesystem = move |entry : &DirEntry| {
+        root_device.is_some()
+            && on_other_device(entry.metadata().ok().and_then(|x| device_id(&x)), root_device)
+    };
+    WalkDir::new(root_directory)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(move |x| !(other_filesystem(x) || options.stop_at_nested_git && is_nested_git_root(x)))
+        .filter_map(|x| x.ok())
+}
+
 // Search for files containing file_name, starting from parent directory described by root_directory
 pub fn find_files_containing_name(root_directory: &str, file_name : &str) -> Vec<PathBuf> {
-    WalkDir::new(root_directory)
+    find_files_containing_name_with_options(root_directory, file_name, SearchOptions::default())
+}
+
+// As find_files_containing_name, with the walk refined by options
+pub fn find_files_containing_name_with_options(root_directory: &str, file_name : &str, options : SearchOptions) -> Vec<PathBuf> {
+    walk(root_directory, options)
+        .filter(|x| os_str_contains_name(x.file_name(), file_name))
+        .map(|x| x.into_path())
+        .collect()
+}
+
+// Search for files containing any of the terms in their name, listing which terms each file matched
+// (in the order the terms were given). Files matching no term are left out
+pub fn find_files_matching_terms(root_directory: &str, terms : &[&str]) -> Vec<(PathBuf, Vec<String>)> {
+    walk(root_directory, SearchOptions::default())
+        .filter_map(|x| {
+            let matched: Vec<String> = terms
+                .iter()
+                .filter(|term| os_str_contains_name(x.file_name(), term))
+                .map(|term| term.to_string())
+                .collect();
+            (!matched.is_empty()).then(|| (x.into_path(), matched))
+        })
+        .collect()
+}
+
+// Apply the name match to an explicit list of paths instead of walking a directory
+// (e.g. to post-filter another command's output). Paths which don't exist are skipped with a warning
+pub fn filter_files_containing_name(paths: &[PathBuf], file_name : &str) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|x| {
+            let exists = x.exists();
+            if !exists {
+                eprintln!("Skipping {}: no such file", x.display());
+            }
+            exists
+        })
+        .filter(|x| x.file_name().is_some_and(|name| os_str_contains_name(name, file_name)))
+        .cloned()
+        .collect()
+}
+
+// As find_files_containing_name, but the subdirectories of root_directory are walked in parallel.
+// Matches are gathered into a set ordered by path, so the output is the same on every run
+// (and the same as the serial search, whose walk is sorted by name too)
+pub fn find_files_containing_name_parallel(root_directory: &str, file_name : &str) -> Vec<PathBuf> {
+    let root = Path::new(root_directory);
+    let mut matches = BTreeSet::new();
+    if root.file_name().is_some_and(|x| os_str_contains_name(x, file_name)) {
+        matches.insert(root.to_path_buf());
+    }
+
+    // Each worker walks a share of the entries directly below the root
+    let children: Vec<PathBuf> = std::fs::read_dir(root)
         .into_iter()
+        .flatten()
         .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .collect();
+    let workers = thread::available_parallelism().map_or(1, |x| x.get());
+    let chunk_size = children.len().div_ceil(workers).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = children
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .flat_map(|child| WalkDir::new(child).into_iter().filter_map(|x| x.ok()))
+                        .filter(|x| os_str_contains_name(x.file_name(), file_name))
+                        .map(|x| x.into_path())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            matches.extend(handle.join().expect("Search worker panicked"));
+        }
+    });
+    matches.into_iter().collect()
+}
+
+// Search for files whose path relative to root_directory matches the regex pattern (e.g. `tests/.*\.rs$`)
+// The pattern is compiled once; an invalid pattern returns the compile error
+pub fn find_files_path_regex(root_directory: &str, pattern : &str) -> Result<Vec<PathBuf>, regex::Error> {
+    let regex = Regex::new(pattern)?;
+    Ok(walk(root_directory, SearchOptions::default())
+        .filter(|x| x.depth() > 0)
+        .filter(|x| {
+            x.path()
+                .strip_prefix(root_directory)
+                .is_ok_and(|relative| regex.is_match(&relative.to_string_lossy()))
+        })
+        .map(|x| x.into_path())
+        .collect())
+}
+
+// Search for files whose name matches include_glob (e.g. `*.rs`), dropping any whose full path
+// (root_directory included) matches exclude_regex (e.g. `.*/generated/.*`).
+// Precedence: the include glob selects candidates first, then the exclude regex removes from them,
+// so a file matching both is excluded.
+pub fn find_files_glob_excluding(root_directory: &str, include_glob : &str, exclude_regex : &str) -> Result<Vec<PathBuf>, PatternError> {
+    let include = Pattern::new(include_glob)?;
+    let exclude = Regex::new(exclude_regex)?;
+    Ok(walk(root_directory, SearchOptions::default())
+        .filter(|x| include.matches(&x.file_name().to_string_lossy()))
+        .filter(|x| !exclude.is_match(&x.path().to_string_lossy()))
+        .map(|x| x.into_path())
+        .collect())
+}
+
+// Search for files containing file_name which are at most max_bytes long, e.g. to find small
+// config or text files while skipping large binaries. Only name matches have their size looked up;
+// directories (and files whose size can't be read) are left out
+pub fn find_small_files(root_directory: &str, file_name : &str, max_bytes : u64) -> Vec<PathBuf> {
+    walk(root_directory, SearchOptions::default())
+        .filter(|x| x.file_type().is_file())
         .filter(|x| os_str_contains_name(x.file_name(), file_name))
+        .filter(|x| x.metadata().is_ok_and(|metadata| metadata.len() <= max_bytes))
         .map(|x| x.into_path())
         .collect()
 }
 
+// Count the files containing file_name, grouped by extension ("(none)" for files without one)
+pub fn count_matches_by_extension(root_directory: &str, file_name : &str) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for entry in walk(root_directory, SearchOptions::default())
+        .filter(|x| x.file_type().is_file())
+        .filter(|x| os_str_contains_name(x.file_name(), file_name))
+    {
+        let extension = entry
+            .path()
+            .extension()
+            .map_or_else(|| "(none)".to_string(), |x| x.to_string_lossy().into_owned());
+        *counts.entry(extension).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Count the files containing file_name, grouped by the first directory under root_directory
+// ("(root)" for matches directly in it)
+pub fn count_matches_by_top_directory(root_directory: &str, file_name : &str) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for entry in walk(root_directory, SearchOptions::default())
+        .filter(|x| x.file_type().is_file())
+        .filter(|x| os_str_contains_name(x.file_name(), file_name))
+    {
+        // A file's depth is 1 when it sits directly in the root, so only deeper files have a directory
+        let directory = match entry.path().strip_prefix(root_directory).ok().and_then(|x| x.components().next()) {
+            Some(component) if entry.depth() > 1 => component.as_os_str().to_string_lossy().into_owned(),
+            _ => "(root)".to_string(),
+        };
+        *counts.entry(directory).or_insert(0) += 1;
+    }
+    counts
+}
+
+// 64 bit FNV-1a, a simple hash which is stable across platforms and releases
+fn fnv1a(hash : u64, bytes : &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3))
+}
+
+// Stable hex digest of a set of paths, e.g. to check in CI that a directory's files haven't changed.
+// Paths are sorted first so the order they were found in doesn't matter.
+// With include_metadata, each file's size and modification time are hashed too (missing metadata hashes as zero)
+pub fn fingerprint(paths : &[PathBuf], include_metadata : bool) -> String {
+    let mut sorted: Vec<_> = paths.iter().collect();
+    sorted.sort();
+
+    let mut hash = 0xcbf29ce484222325;
+    for path in sorted {
+        hash = fnv1a(hash, path.to_string_lossy().as_bytes());
+        if include_metadata {
+            let metadata = path.metadata().ok();
+            let size = metadata.as_ref().map_or(0, |x| x.len());
+            let modified = metadata
+                .and_then(|x| x.modified().ok())
+                .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |x| x.as_nanos());
+            hash = fnv1a(hash, &size.to_le_bytes());
+            hash = fnv1a(hash, &modified.to_le_bytes());
+        }
+        // Separate entries, so ["ab", "c"] and ["a", "bc"] differ
+        hash = fnv1a(hash, b"\n");
+    }
+    format!("{hash:016x}")
+}
+
+// Run git with args in directory, returning its output. None if git couldn't run or failed
+// (e.g. the directory isn't in a repository)
+fn git_output(directory : &str, args : &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(directory).args(args).output().ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+// Absolute path of path, with its directory's symlinks resolved (as git reports the repository's top)
+fn resolved_path(path : &Path) -> Option<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            Some(parent.canonicalize().ok()?.join(name))
+        }
+        (_, Some(name)) => Some(std::env::current_dir().ok()?.canonicalize().ok()?.join(name)),
+        _ => path.canonicalize().ok(),
+    }
+}
+
+// Status of each changed file in the repository containing root_directory, keyed by absolute path.
+// Returns the repository's top directory too. None outside a repository
+fn git_statuses(root_directory : &str) -> Option<(PathBuf, BTreeMap<PathBuf, GitStatus>)> {
+    let top = git_output(root_directory, &["rev-parse", "--show-toplevel"])?;
+    let top = PathBuf::from(String::from_utf8_lossy(&top).trim_end()).canonicalize().ok()?;
+
+    // Porcelain paths are relative to the top of the repository; -z leaves them unquoted
+    let porcelain = git_output(root_directory, &["status", "--porcelain", "-z", "--untracked-files=all"])?;
+    let mut statuses = BTreeMap::new();
+    let mut entries = porcelain.split(|x| *x == 0).filter(|x| x.len() > 3);
+    while let Some(entry) = entries.next() {
+        let (index, worktree) = (entry[0], entry[1]);
+        let status = match (index, worktree) {
+            (b'?', b'?') => GitStatus::Untracked,
+            (_, b' ') => GitStatus::Staged,
+            _ => GitStatus::Modified,
+        };
+        statuses.insert(top.join(String::from_utf8_lossy(&entry[3..]).as_ref()), status);
+        // Renames and copies are followed by the path they came from
+        if matches!(index, b'R' | b'C') {
+            entries.next();
+        }
+    }
+    Some((top, statuses))
+}
+
+// Annotate each path with its git status, running `git status` once for the repository
+// containing root_directory. Paths without changes are Clean if they're inside that repository
+pub fn annotate_git_status(root_directory : &str, paths : &[PathBuf]) -> Vec<(PathBuf, GitStatus)> {
+    let repository = git_statuses(root_directory);
+    paths
+        .iter()
+        .map(|path| {
+            let status = match (&repository, resolved_path(path)) {
+                (Some((top, statuses)), Some(resolved)) if resolved.starts_with(top) => {
+                    statuses.get(&resolved).copied().unwrap_or(GitStatus::Clean)
+                }
+                _ => GitStatus::NotInRepository,
+            };
+            (path.clone(), status)
+        })
+        .collect()
+}
+
+// How much of the file tail_lines reads at a time, working back from the end
+const TAIL_CHUNK_SIZE: u64 = 4096;
+
+// Newlines separating lines (one ending the file doesn't start another line)
+fn line_breaks(bytes : &[u8]) -> usize {
+    bytes.strip_suffix(b"\n").unwrap_or(bytes).iter().filter(|x| **x == b'\n').count()
+}
+
+// The last `lines` lines of a text file. Reads backwards from the end in chunks,
+// so only the tail of a large file is read. Binary (or non UTF-8) files are an InvalidData error
+pub fn tail_lines(path : &Path, lines : usize) -> io::Result<Vec<String>> {
+    if lines == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut file = File::open(path)?;
+    let mut position = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    // As many line breaks as lines wanted means the first line is complete
+    while position > 0 && line_breaks(&tail) < lines {
+        let chunk_size = TAIL_CHUNK_SIZE.min(position);
+        position -= chunk_size;
+        file.seek(SeekFrom::Start(position))?;
+        let mut chunk = vec![0; chunk_size as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.append(&mut tail);
+        tail = chunk;
+    }
+
+    if tail.contains(&0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "binary file"));
+    }
+    let content = tail.strip_suffix(b"\n").unwrap_or(&tail);
+    let all_lines: Vec<_> = content.split(|x| *x == b'\n').collect();
+    all_lines[all_lines.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            String::from_utf8(line.to_vec())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a UTF-8 text file"))
+        })
+        .collect()
+}
+
+// Search for files containing file_name again each time entries below root_directory are created,
+// renamed or removed, passing the new matches to on_change. Bursts of events are debounced: the search
+// waits until no event has arrived for the debounce period. Watches until on_change returns false
+#[cfg(feature = "watch")]
+pub fn watch_files_containing_name<F>(root_directory: &str, file_name : &str, debounce : std::time::Duration, mut on_change : F) -> notify::Result<()>
+where
+    F: FnMut(Vec<PathBuf>) -> bool,
+{
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    // Only a change to which entries exist can change the matches
+    let changes_entries = |event : &notify::Result<notify::Event>| match event {
+        Ok(event) => matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(notify::event::ModifyKind::Name(_))
+        ),
+        Err(_) => false,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(Path::new(root_directory), RecursiveMode::Recursive)?;
+    for event in &rx {
+        if !changes_entries(&event) {
+            continue;
+        }
+        loop {
+            match rx.recv_timeout(debounce) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+        if !on_change(find_files_containing_name(root_directory, file_name)) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Starts the first line of a cache, followed by the root's modification time and the root
+const CACHE_HEADER: &str = "# find_files cache ";
+
+// Modification time of the root directory, in nanoseconds since the epoch
+fn modified_nanos(root_directory : &str) -> io::Result<u128> {
+    let modified = Path::new(root_directory).metadata()?.modified()?;
+    Ok(modified.duration_since(UNIX_EPOCH).map_or(0, |x| x.as_nanos()))
+}
+
+// Write every path under root_directory to cache_path, one per line (as `fd` or `rg --files` list them),
+// after a header line recording the root and its modification time. Returns how many paths were written
+pub fn build_cache(root_directory: &str, cache_path : &Path) -> io::Result<usize> {
+    let mut cache = BufWriter::new(File::create(cache_path)?);
+    writeln!(cache, "{CACHE_HEADER}{} {root_directory}", modified_nanos(root_directory)?)?;
+    let mut written = 0;
+    for entry in walk(root_directory, SearchOptions::default()).filter(|x| x.depth() > 0) {
+        writeln!(cache, "{}", entry.path().display())?;
+        written += 1;
+    }
+    cache.flush()?;
+    Ok(written)
+}
+
+// The paths of a cache built for root_directory. A cache for another root, or one which is stale
+// (the root was modified since, e.g. an entry added to or removed from it) is an InvalidData error.
+// Only the root's own modification time is checked, so changes deeper down go unnoticed
+pub fn read_cache(root_directory: &str, cache_path : &Path) -> io::Result<Vec<PathBuf>> {
+    let invalid = |message : &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut lines = BufReader::new(File::open(cache_path)?).lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let (modified, root) = header
+        .strip_prefix(CACHE_HEADER)
+        .and_then(|x| x.split_once(' '))
+        .ok_or_else(|| invalid("not a find_files cache"))?;
+    if root != root_directory {
+        return Err(invalid(&format!("cache was built for {root}")));
+    }
+    if modified != modified_nanos(root_directory)?.to_string() {
+        return Err(invalid("cache is stale, build it again"));
+    }
+    lines.map(|line| line.map(PathBuf::from)).collect()
+}
+
+// Search cached paths for files containing file_name, as find_files_containing_name would the tree
+pub fn find_files_in_cache(paths : &[PathBuf], file_name : &str) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|x| x.file_name().is_some_and(|name| os_str_contains_name(name, file_name)))
+        .cloned()
+        .collect()
+}
+
+// Where a copy of source goes in destination: its own file name, or with a numeric suffix
+// (notes_1.txt, notes_2.txt, ...) when that's already taken
+fn copy_destination(source : &Path, destination : &Path) -> Option<PathBuf> {
+    let name = Path::new(source.file_name()?);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name.extension().map(|x| format!(".{}", x.to_string_lossy())).unwrap_or_default();
+    (0..)
+        .map(|suffix| match suffix {
+            0 => destination.join(name),
+            _ => destination.join(format!("{stem}_{suffix}{extension}")),
+        })
+        .find(|x| !x.exists())
+}
+
+// Copy each of paths into the destination directory (created if needed), renaming copies whose
+// name is taken. Each path is returned with where it was copied to, or why it couldn't be.
+// Refuses with InvalidInput when destination is inside root_directory, as a later search
+// from the root would find (and copy) the copies again
+pub fn copy_files_to(root_directory: &str, paths : &[PathBuf], destination : &Path) -> io::Result<Vec<(PathBuf, io::Result<PathBuf>)>> {
+    let root = Path::new(root_directory).canonicalize()?;
+    let target = destination.canonicalize().or_else(|_| std::path::absolute(destination))?;
+    if target.starts_with(&root) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is inside the search root {}", destination.display(), root_directory),
+        ));
+    }
+    fs::create_dir_all(destination)?;
+
+    Ok(paths
+        .iter()
+        .map(|path| {
+            let copied = copy_destination(path, destination)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))
+                .and_then(|copy| fs::copy(path, &copy).map(|_| copy));
+            (path.clone(), copied)
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn other_device_compared_by_id() {
+        assert!(!on_other_device(Some(1), Some(1)));
+        assert!(on_other_device(Some(2), Some(1)));
+
+        // Without both ids (e.g. outside Unix) nothing is pruned
+        assert!(!on_other_device(None, Some(1)));
+        assert!(!on_other_device(Some(2), None));
+        assert!(!on_other_device(None, None));
+    }
+
     #[test]
     fn match_os_str_with_name() {
         // Construct the os_str to test (as a file name)
@@ -41,4 +604,21 @@ mod tests {
         // Expect failure when matching against a path
         assert!(!os_str_contains_name(os_file_name, "tests/assets/sample.txt"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fnv1a_known_values() {
+        // Reference values of 64 bit FNV-1a
+        assert_eq!(fnv1a(0xcbf29ce484222325, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(0xcbf29ce484222325, b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn match_ranges_of_name() {
+        // Every occurrence is reported
+        assert_eq!(match_ranges("sample_sample.txt", "sam"), vec![0..3, 7..10]);
+
+        // No occurrence, or nothing to look for, reports no ranges
+        assert!(match_ranges("sample.txt", "reject").is_empty());
+        assert!(match_ranges("sample.txt", "").is_empty());
+    }
+}
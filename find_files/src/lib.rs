@@ -1 +1,2 @@
 pub mod find_files;
+pub mod repl;
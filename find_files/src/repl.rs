@@ -0,0 +1,471 @@
+//! A command line tool to test finding files with a given filename substring!
+//!
+
+use crate::find_files::{annotate_git_status, build_cache, copy_files_to, find_files_in_cache, read_cache, count_matches_by_extension, count_matches_by_top_directory, find_files_containing_name, find_files_matching_terms, fingerprint, match_ranges, tail_lines};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, IsTerminal},
+    ops::Range,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+};
+
+const HIGHLIGHT_START: &str = "\x1b[1;31m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+// OSC 8 hyperlink escape sequences: the start is followed by the link target, then LINK_TEXT
+const LINK_START: &str = "\x1b]8;;";
+const LINK_TEXT: &str = "\x1b\\";
+
+/// Entry point of the interactive search. Reads search terms from stdin until 'q' is entered.
+pub fn run_find() {
+    let color = use_color();
+    instruction();
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        match line.as_deref().map(parse_query) {
+            Ok(Ok(Query::Quit)) => {
+                return;
+            }
+            Ok(Ok(Query::Search(file_name))) => {
+                let matching_files = find_files_containing_name(".", file_name);
+                for file in matching_files {
+                    println!("{}", format_match(&file, file_name, color));
+                }
+            }
+            Ok(Ok(Query::Absolute(file_name))) => {
+                let home = home_directory();
+                for file in find_files_containing_name(".", file_name) {
+                    let file = std::path::absolute(&file).unwrap_or(file);
+                    let display = PathBuf::from(abbreviate_home(&file, home.as_deref()));
+                    println!("{}", format_match(&display, file_name, color));
+                }
+            }
+            Ok(Ok(Query::Hyperlinks(file_name))) => {
+                for file in find_files_containing_name(".", file_name) {
+                    let text = format_match(&file, file_name, color);
+                    match std::path::absolute(&file) {
+                        Ok(absolute) if color => println!("{}", hyperlink(&absolute, &text)),
+                        _ => println!("{text}"),
+                    }
+                }
+            }
+            Ok(Ok(Query::MakeTarget { target, file_name })) => {
+                println!("{}", format_make_rule(target, &find_files_containing_name(".", file_name)));
+            }
+            Ok(Ok(Query::Watch(file_name))) => {
+                for file in find_files_containing_name(".", file_name) {
+                    println!("{}", format_match(&file, file_name, color));
+                }
+                watch(file_name, color);
+            }
+            Ok(Ok(Query::CountByExtension(file_name))) => {
+                println!("{}", format_counts(&count_matches_by_extension(".", file_name)));
+            }
+            Ok(Ok(Query::CountByDirectory(file_name))) => {
+                println!("{}", format_counts(&count_matches_by_top_directory(".", file_name)));
+            }
+            Ok(Ok(Query::Fingerprint(file_name))) => {
+                println!("{}", fingerprint(&find_files_containing_name(".", file_name), true));
+            }
+            Ok(Ok(Query::GitStatus(file_name))) => {
+                let matching_files = find_files_containing_name(".", file_name);
+                for (file, status) in annotate_git_status(".", &matching_files) {
+                    println!("{} [{status}]", format_match(&file, file_name, color));
+                }
+            }
+            Ok(Ok(Query::Terms(terms))) => {
+                for (file, matched) in find_files_matching_terms(".", &terms) {
+                    println!("{} [{}]", file.display(), matched.join(", "));
+                }
+            }
+            Ok(Ok(Query::CopyTo { destination, file_name })) => {
+                let files: Vec<_> = find_files_containing_name(".", file_name).into_iter().filter(|x| x.is_file()).collect();
+                print_copies(&files, Path::new(destination));
+            }
+            Ok(Ok(Query::BuildCache(cache_path))) => {
+                match build_cache(".", Path::new(cache_path)) {
+                    Ok(written) => println!("Cached {written} paths in {cache_path}"),
+                    Err(error) => println!("Can't build the cache: {error}"),
+                }
+            }
+            Ok(Ok(Query::UseCache { cache_path, file_name })) => {
+                match read_cache(".", Path::new(cache_path)) {
+                    Ok(paths) => {
+                        for file in find_files_in_cache(&paths, file_name) {
+                            println!("{}", format_match(&file, file_name, color));
+                        }
+                    }
+                    Err(error) => println!("Can't use the cache: {error}"),
+                }
+            }
+            Ok(Ok(Query::Tail { lines, file_name })) => {
+                for file in find_files_containing_name(".", file_name).iter().filter(|x| x.is_file()) {
+                    print_tail(file, lines);
+                }
+            }
+            Ok(Err(message)) => {
+                println!("{message}");
+            }
+            Err(err) => {
+                println!("Got error {:?}", err);
+                println!("Expectect partial file path or name. Press q to exit");
+            }
+        }
+        instruction();
+    }
+}
+
+// A line of input: the search term, optionally preceded by an option changing what is printed
+#[derive(Debug, PartialEq)]
+enum Query<'a> {
+    Quit,
+    Search(&'a str),
+    Absolute(&'a str),
+    Hyperlinks(&'a str),
+    Watch(&'a str),
+    CountByExtension(&'a str),
+    CountByDirectory(&'a str),
+    Fingerprint(&'a str),
+    GitStatus(&'a str),
+    Terms(Vec<&'a str>),
+    Tail { lines : usize, file_name : &'a str },
+    CopyTo { destination : &'a str, file_name : &'a str },
+    MakeTarget { target : &'a str, file_name : &'a str },
+    BuildCache(&'a str),
+    UseCache { cache_path : &'a str, file_name : &'a str },
+}
+
+fn parse_query(line : &str) -> Result<Query<'_>, String> {
+    if line == "q" {
+        Ok(Query::Quit)
+    } else if let Some(file_name) = line.strip_prefix("--absolute ") {
+        Ok(Query::Absolute(file_name))
+    } else if let Some(file_name) = line.strip_prefix("--hyperlinks ") {
+        Ok(Query::Hyperlinks(file_name))
+    } else if let Some(file_name) = line.strip_prefix("--watch ") {
+        Ok(Query::Watch(file_name))
+    } else if let Some(file_name) = line.strip_prefix("--count-by-ext ") {
+        Ok(Query::CountByExtension(file_name))
+    } else if let Some(file_name) = line.strip_prefix("--count-by-dir ") {
+        Ok(Query::CountByDirectory(file_name))
+    } else if let Some(file_name) = line.strip_prefix("--fingerprint ") {
+        Ok(Query::Fingerprint(file_name))
+    } else if let Some(file_name) = line.strip_prefix("--git-status ") {
+        Ok(Query::GitStatus(file_name))
+    } else if let Some(terms) = line.strip_prefix("--terms ") {
+        Ok(Query::Terms(terms.split_whitespace().collect()))
+    } else if let Some(arguments) = line.strip_prefix("--copy-to ") {
+        match arguments.split_once(' ') {
+            Some((destination, file_name)) => Ok(Query::CopyTo { destination, file_name }),
+            None => Err("Expected a directory and a search term after --copy-to".to_string()),
+        }
+    } else if let Some(arguments) = line.strip_prefix("--make-target ") {
+        match arguments.split_once(' ') {
+            Some((target, file_name)) => Ok(Query::MakeTarget { target, file_name }),
+            None => Err("Expected a target name and a search term after --make-target".to_string()),
+        }
+    } else if let Some(cache_path) = line.strip_prefix("--build-cache ") {
+        Ok(Query::BuildCache(cache_path))
+    } else if let Some(arguments) = line.strip_prefix("--use-cache ") {
+        match arguments.split_once(' ') {
+            Some((cache_path, file_name)) => Ok(Query::UseCache { cache_path, file_name }),
+            None => Err("Expected a cache file and a search term after --use-cache".to_string()),
+        }
+    } else if let Some(arguments) = line.strip_prefix("--tail ") {
+        let (lines, file_name) = arguments.split_once(' ').unwrap_or((arguments, ""));
+        match lines.parse() {
+            Ok(lines) => Ok(Query::Tail { lines, file_name }),
+            Err(_) => Err(format!("Expected a number of lines after --tail, got '{lines}'")),
+        }
+    } else {
+        Ok(Query::Search(line))
+    }
+}
+
+fn instruction() {
+    println!("Enter a search term. Search will run from the current directory and return a list of all files that contain a substring in the filename matching the given search string! Press 'q' to exit");
+    println!("Options (entered before the search term):");
+    println!("  --absolute      print absolute paths, with the home directory shortened to ~");
+    println!("  --hyperlinks    make each match a clickable file:// link (in terminals supporting OSC 8)");
+    println!("  --watch         search again whenever files are created, renamed or removed (Ctrl-C to stop)");
+    println!("  --count-by-ext  count matches per extension");
+    println!("  --count-by-dir  count matches per top-level directory");
+    println!("  --fingerprint   print a hash of the matched paths, sizes and modification times");
+    println!("  --git-status    mark each match as modified, staged, untracked, clean (or n/a outside a repository)");
+    println!("  --terms         search for several space separated terms, listing the terms each file matched");
+    println!("  --tail N        print the last N lines of each matched file");
+    println!("  --make-target T print a Makefile rule for target T, with the matches as prerequisites");
+    println!("  --build-cache F list every path (for later searches, or other tools) in file F, with no search term");
+    println!("  --use-cache F   search the paths listed in F instead of the directory");
+    println!("  --copy-to DIR   copy each matched file into DIR (outside the current directory)");
+}
+
+// Copy the files into destination, printing where each went (or why it didn't)
+fn print_copies(files : &[PathBuf], destination : &Path) {
+    match copy_files_to(".", files, destination) {
+        Ok(copies) => {
+            let copied = copies.iter().filter(|(_, copy)| copy.is_ok()).count();
+            for (file, copy) in copies {
+                match copy {
+                    Ok(copy) => println!("Copied {} to {}", file.display(), copy.display()),
+                    Err(error) => println!("Failed to copy {}: {error}", file.display()),
+                }
+            }
+            println!("Copied {copied} of {} files", files.len());
+        }
+        Err(error) => println!("Can't copy to {}: {error}", destination.display()),
+    }
+}
+
+// Print the matches again whenever they may have changed, until interrupted
+#[cfg(feature = "watch")]
+fn watch(file_name : &str, color : bool) {
+    let debounce = std::time::Duration::from_millis(200);
+    let result = crate::find_files::watch_files_containing_name(".", file_name, debounce, |matching_files| {
+        println!("-- files changed --");
+        for file in matching_files {
+            println!("{}", format_match(&file, file_name, color));
+        }
+        true
+    });
+    if let Err(error) = result {
+        println!("Can't watch for changes: {error}");
+    }
+}
+
+#[cfg(not(feature = "watch"))]
+fn watch(_file_name : &str, _color : bool) {
+    println!("Watching needs the 'watch' feature (cargo build --features watch)");
+}
+
+// Print the last lines of the file under a header, or a note why it was skipped
+fn print_tail(path : &Path, lines : usize) {
+    println!("==> {} <==", path.display());
+    match tail_lines(path, lines) {
+        Ok(tail) => {
+            for line in tail {
+                println!("{line}");
+            }
+        }
+        Err(error) => println!("(skipped: {error})"),
+    }
+}
+
+// The user's home directory, from HOME (or USERPROFILE on Windows)
+fn home_directory() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+}
+
+// Shorten a path within home to ~/..., leaving paths outside home as they are
+fn abbreviate_home(path : &Path, home : Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~{MAIN_SEPARATOR}{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+// Percent encode a path for a file:// URL, leaving unreserved characters and separators as they are
+fn encode_path(path : &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char);
+            }
+            // Windows separators; elsewhere a backslash is part of the name
+            b'\\' if MAIN_SEPARATOR == '\\' => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+// Wrap text in an OSC 8 hyperlink to the (absolute) path, for terminals to open on click
+fn hyperlink(absolute_path : &Path, text : &str) -> String {
+    let mut target = encode_path(absolute_path);
+    // Windows paths (C:/...) need the slash of an empty host in front
+    if !target.starts_with('/') {
+        target.insert(0, '/');
+    }
+    format!("{LINK_START}file://{target}{LINK_TEXT}{text}{LINK_START}{LINK_TEXT}")
+}
+
+// Format per extension counts on one line, e.g. "rs: 4, txt: 1"
+fn format_counts(counts : &BTreeMap<String, usize>) -> String {
+    if counts.is_empty() {
+        return "No matching files".to_string();
+    }
+    counts
+        .iter()
+        .map(|(extension, count)| format!("{extension}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Escape a file name for a Makefile rule: spaces and # with a backslash, $ doubled
+fn escape_make(name : &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for character in name.chars() {
+        match character {
+            ' ' | '#' => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            '$' => escaped.push_str("$$"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+// A Makefile rule for target, with each path as a prerequisite (`target: dep1 dep2`)
+fn format_make_rule(target : &str, paths : &[PathBuf]) -> String {
+    let mut rule = format!("{}:", escape_make(target));
+    for path in paths {
+        rule.push(' ');
+        rule.push_str(&escape_make(&path.to_string_lossy()));
+    }
+    rule
+}
+
+// Color unless the output isn't a terminal, or the user opted out (https://no-color.org)
+fn use_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && std::io::stdout().is_terminal()
+}
+
+// Format the path for printing, highlighting occurrences of the search term within its file name
+fn format_match(path : &Path, file_name : &str, color : bool) -> String {
+    let path_string = path.to_string_lossy();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if !color {
+        return path_string.into_owned();
+    }
+
+    // The file name is the tail of the path, so shift its ranges into place
+    let offset = path_string.len() - name.len();
+    let ranges: Vec<_> = match_ranges(&name, file_name)
+        .into_iter()
+        .map(|range| range.start + offset..range.end + offset)
+        .collect();
+    highlight(&path_string, &ranges)
+}
+
+// Wrap each range of text in color escape sequences. Ranges must be ordered and not overlap
+fn highlight(text : &str, ranges : &[Range<usize>]) -> String {
+    let mut highlighted = String::with_capacity(text.len());
+    let mut position = 0;
+    for range in ranges {
+        highlighted.push_str(&text[position..range.start]);
+        highlighted.push_str(HIGHLIGHT_START);
+        highlighted.push_str(&text[range.clone()]);
+        highlighted.push_str(HIGHLIGHT_END);
+        position = range.end;
+    }
+    highlighted.push_str(&text[position..]);
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_matched_substrings() {
+        let path = Path::new("tests/assets/sample1.txt");
+        assert_eq!(
+            format_match(path, "sam", true),
+            "tests/assets/\x1b[1;31msam\x1b[0mple1.txt"
+        );
+
+        // Only the file name is highlighted, even when a directory also matches
+        let path = Path::new("sam/sample_sample.txt");
+        assert_eq!(
+            format_match(path, "sam", true),
+            "sam/\x1b[1;31msam\x1b[0mple_\x1b[1;31msam\x1b[0mple.txt"
+        );
+    }
+
+    #[test]
+    fn counts_on_one_line() {
+        let counts = BTreeMap::from([("rs".to_string(), 4), ("txt".to_string(), 1)]);
+        assert_eq!(format_counts(&counts), "rs: 4, txt: 1");
+        assert_eq!(format_counts(&BTreeMap::new()), "No matching files");
+    }
+
+    #[test]
+    fn parse_query_options() {
+        assert_eq!(parse_query("q"), Ok(Query::Quit));
+        assert_eq!(parse_query("sample"), Ok(Query::Search("sample")));
+        assert_eq!(parse_query("--absolute sam"), Ok(Query::Absolute("sam")));
+        assert_eq!(parse_query("--hyperlinks sam"), Ok(Query::Hyperlinks("sam")));
+        assert_eq!(parse_query("--watch sam"), Ok(Query::Watch("sam")));
+        assert_eq!(parse_query("--count-by-ext rs"), Ok(Query::CountByExtension("rs")));
+        assert_eq!(parse_query("--count-by-dir rs"), Ok(Query::CountByDirectory("rs")));
+        assert_eq!(parse_query("--fingerprint sam"), Ok(Query::Fingerprint("sam")));
+        assert_eq!(parse_query("--git-status src"), Ok(Query::GitStatus("src")));
+        assert_eq!(parse_query("--terms test  helper"), Ok(Query::Terms(vec!["test", "helper"])));
+        assert_eq!(parse_query("--tail 5 log"), Ok(Query::Tail { lines: 5, file_name: "log" }));
+        assert!(parse_query("--tail many log").is_err());
+        assert_eq!(
+            parse_query("--copy-to /tmp/out sam"),
+            Ok(Query::CopyTo { destination: "/tmp/out", file_name: "sam" })
+        );
+        assert!(parse_query("--copy-to /tmp/out").is_err());
+        assert_eq!(parse_query("--build-cache index.txt"), Ok(Query::BuildCache("index.txt")));
+        assert_eq!(
+            parse_query("--use-cache index.txt sam"),
+            Ok(Query::UseCache { cache_path: "index.txt", file_name: "sam" })
+        );
+        assert_eq!(
+            parse_query("--make-target all sam"),
+            Ok(Query::MakeTarget { target: "all", file_name: "sam" })
+        );
+    }
+
+    #[test]
+    fn home_directory_abbreviated() {
+        let home = Path::new("/home/alice");
+        let abbreviate = |path : &str| abbreviate_home(Path::new(path), Some(home));
+        assert_eq!(abbreviate("/home/alice/src/main.rs"), "~/src/main.rs");
+        assert_eq!(abbreviate("/home/alice"), "~");
+
+        // Only whole directories count, and other paths are left alone
+        assert_eq!(abbreviate("/home/alice2/notes.txt"), "/home/alice2/notes.txt");
+        assert_eq!(abbreviate("/etc/hosts"), "/etc/hosts");
+        assert_eq!(abbreviate_home(Path::new("/home/alice/a.txt"), None), "/home/alice/a.txt");
+    }
+
+    #[test]
+    fn hyperlink_wraps_encoded_path() {
+        let path = Path::new("/tmp/my notes/50%.txt");
+        assert_eq!(
+            hyperlink(path, "notes"),
+            "\x1b]8;;file:///tmp/my%20notes/50%25.txt\x1b\\notes\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[test]
+    fn make_rule_lists_matches() {
+        let paths = find_files_containing_name("tests/assets", "sample");
+        assert_eq!(
+            format_make_rule("samples", &paths),
+            "samples: tests/assets/sample1.txt tests/assets/sample2.txt"
+        );
+
+        // Spaces, comments and variables are escaped
+        let paths = [PathBuf::from("my notes/#1 $HOME.txt")];
+        assert_eq!(format_make_rule("all", &paths), "all: my\\ notes/\\#1\\ $$HOME.txt");
+        assert_eq!(format_make_rule("all", &[]), "all:");
+    }
+
+    #[test]
+    fn plain_output_without_color() {
+        let path = Path::new("tests/assets/sample1.txt");
+        assert_eq!(format_match(path, "sam", false), "tests/assets/sample1.txt");
+    }
+}
@@ -1,6 +1,18 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use find_files::find_files::find_files_containing_name;
+use find_files::find_files::{
+    annotate_git_status, build_cache, copy_files_to, count_matches_by_extension,
+    count_matches_by_top_directory, filter_files_containing_name, find_files_containing_name,
+    find_files_containing_name_parallel, find_files_containing_name_with_options,
+    find_files_glob_excluding, find_files_in_cache, find_files_matching_terms,
+    find_files_path_regex, find_small_files, fingerprint, read_cache, tail_lines, GitStatus,
+    PatternError, SearchOptions,
+};
 
 #[test]
 fn found_file_containing_name() {
@@ -32,3 +44,492 @@ fn no_files_containing_name() {
 
     assert!(maybe_paths.is_empty());
 }
+
+#[test]
+fn nested_git_repository_excluded() {
+    // A nested `.git` directory can't be committed as a fixture, so build the tree here
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("vendored/.git")).unwrap();
+    fs::write(root.path().join("top_sample.txt"), "").unwrap();
+    fs::write(root.path().join("vendored/nested_sample.txt"), "").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    // By default the walk crosses into the nested repository
+    let maybe_paths = find_files_containing_name(root_directory, "sample");
+    assert_eq!(maybe_paths.len(), 2);
+
+    let options = SearchOptions {
+        stop_at_nested_git: true,
+        ..SearchOptions::default()
+    };
+    let maybe_paths = find_files_containing_name_with_options(root_directory, "sample", options);
+    assert_eq!(vec![root.path().join("top_sample.txt")], maybe_paths);
+}
+
+#[test]
+fn same_filesystem_walk_keeps_local_files() {
+    // A temporary directory has no mount points below it, so nothing is pruned
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir(root.path().join("nested")).unwrap();
+    fs::write(root.path().join("nested/sample.txt"), "a").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    let options = SearchOptions {
+        same_filesystem: true,
+        ..SearchOptions::default()
+    };
+    assert_eq!(
+        find_files_containing_name_with_options(root_directory, "sample", options),
+        find_files_containing_name(root_directory, "sample")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn same_filesystem_walk_skips_mounts() {
+    use std::os::unix::fs::MetadataExt;
+
+    // Filesystems such as /dev/pts or /dev/shm are usually mounted below /dev
+    let Ok(root) = Path::new("/dev").metadata() else {
+        return;
+    };
+    let mounts: Vec<PathBuf> = fs::read_dir("/dev")
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.ok())
+        .filter(|x| {
+            x.metadata()
+                .is_ok_and(|x| x.is_dir() && x.dev() != root.dev())
+        })
+        .map(|x| x.path())
+        .collect();
+
+    let options = SearchOptions {
+        same_filesystem: true,
+        ..SearchOptions::default()
+    };
+    for mount in mounts {
+        let name = mount.file_name().unwrap().to_str().unwrap();
+        let maybe_paths = find_files_containing_name_with_options("/dev", name, options);
+        assert!(!maybe_paths.iter().any(|x| x.starts_with(&mount)));
+    }
+}
+
+#[test]
+fn files_matching_path_regex() {
+    // Anchored to the directory relative to the root, not just the file name
+    let maybe_paths = find_files_path_regex("tests", r"^assets/sample\d\.txt$").unwrap();
+
+    let sample1_path = PathBuf::from("tests/assets/sample1.txt");
+    let sample2_path = PathBuf::from("tests/assets/sample2.txt");
+    assert_eq!(vec![sample1_path, sample2_path], maybe_paths);
+
+    // The file name alone doesn't satisfy a pattern anchored at the root
+    let maybe_paths = find_files_path_regex("tests", r"^sample1\.txt$").unwrap();
+    assert!(maybe_paths.is_empty());
+}
+
+#[test]
+fn invalid_path_regex_is_an_error() {
+    assert!(find_files_path_regex("tests", "assets/(sample").is_err());
+}
+
+#[test]
+fn include_glob_then_exclude_path_regex() {
+    // The generated notes match the include glob...
+    let maybe_paths = find_files_glob_excluding("tests/assets", "*.txt", "^$").unwrap();
+    assert!(maybe_paths.contains(&PathBuf::from("tests/assets/generated/notes.txt")));
+
+    // ...but the exclusion takes precedence
+    let maybe_paths =
+        find_files_glob_excluding("tests/assets", "*.txt", ".*/generated/.*").unwrap();
+
+    let sample1_path = PathBuf::from("tests/assets/sample1.txt");
+    let sample2_path = PathBuf::from("tests/assets/sample2.txt");
+    assert_eq!(vec![sample1_path, sample2_path], maybe_paths);
+}
+
+#[test]
+fn invalid_include_or_exclude_pattern_is_an_error() {
+    assert!(matches!(
+        find_files_glob_excluding("tests/assets", "[*.txt", ".*"),
+        Err(PatternError::Glob(_))
+    ));
+    assert!(matches!(
+        find_files_glob_excluding("tests/assets", "*.txt", "(generated"),
+        Err(PatternError::Regex(_))
+    ));
+}
+
+#[test]
+fn only_small_matching_files() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("config_small.toml"), "a = 1\n").unwrap();
+    fs::write(root.path().join("config_exact.toml"), [b'x'; 64]).unwrap();
+    fs::write(root.path().join("config_large.bin"), vec![0; 64 * 1024]).unwrap();
+    fs::write(root.path().join("other.txt"), "small").unwrap();
+    fs::create_dir(root.path().join("config_dir")).unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    // The ceiling is inclusive, and directories are never returned
+    let small = find_small_files(root_directory, "config", 64);
+    assert_eq!(
+        small,
+        vec![
+            root.path().join("config_exact.toml"),
+            root.path().join("config_small.toml")
+        ]
+    );
+}
+
+#[test]
+fn count_matching_files_by_extension() {
+    // Directories are not counted, files without an extension are grouped together
+    let counts = count_matches_by_extension("tests/assets", "e");
+
+    let expected = BTreeMap::from([("(none)".to_string(), 1), ("txt".to_string(), 3)]);
+    assert_eq!(expected, counts);
+
+    // Only name matches are counted
+    let counts = count_matches_by_extension("tests/assets", "sample");
+    assert_eq!(BTreeMap::from([("txt".to_string(), 2)]), counts);
+}
+
+#[test]
+fn count_matching_files_by_top_directory() {
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("crate_a/src/nested")).unwrap();
+    fs::create_dir(root.path().join("crate_b")).unwrap();
+    for file in [
+        "crate_a/sample.rs",
+        "crate_a/src/sample.rs",
+        "crate_a/src/nested/sample.rs",
+        "crate_b/sample.rs",
+        "crate_b/other.rs",
+        "sample.rs",
+    ] {
+        fs::write(root.path().join(file), "").unwrap();
+    }
+
+    let counts = count_matches_by_top_directory(root.path().to_str().unwrap(), "sample");
+    let expected = BTreeMap::from([
+        ("(root)".to_string(), 1),
+        ("crate_a".to_string(), 3),
+        ("crate_b".to_string(), 1),
+    ]);
+    assert_eq!(expected, counts);
+}
+
+#[test]
+fn parallel_search_order_is_deterministic() {
+    let serial_paths = find_files_containing_name("tests", "e");
+    assert!(serial_paths.len() > 2);
+
+    // Every parallel run returns the same paths in the same order as the serial walk
+    for _ in 0..20 {
+        assert_eq!(
+            serial_paths,
+            find_files_containing_name_parallel("tests", "e")
+        );
+    }
+}
+
+#[test]
+fn matched_terms_listed_per_file() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("test_helper.rs"), "").unwrap();
+    fs::write(root.path().join("helper.rs"), "").unwrap();
+    fs::write(root.path().join("main.rs"), "").unwrap();
+    fs::write(root.path().join("testing.rs"), "").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    // "test" and "testing" overlap, and main.rs matches neither term
+    let matches = find_files_matching_terms(root_directory, &["test", "helper", "testing"]);
+    let terms = |terms: &[&str]| terms.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        matches,
+        vec![
+            (root.path().join("helper.rs"), terms(&["helper"])),
+            (
+                root.path().join("test_helper.rs"),
+                terms(&["test", "helper"])
+            ),
+            (root.path().join("testing.rs"), terms(&["test", "testing"])),
+        ]
+    );
+}
+
+#[test]
+fn explicit_file_list_filtered_by_name() {
+    let sample1_path = PathBuf::from("tests/assets/sample1.txt");
+    let sample2_path = PathBuf::from("tests/assets/sample2.txt");
+    let notes_path = PathBuf::from("tests/assets/generated/notes.txt");
+    let missing_path = PathBuf::from("tests/assets/sample3.txt");
+    let paths = vec![
+        sample2_path.clone(),
+        missing_path,
+        notes_path,
+        sample1_path.clone(),
+    ];
+
+    // Missing paths are skipped, the rest keep their given order
+    let maybe_paths = filter_files_containing_name(&paths, "sample");
+    assert_eq!(vec![sample2_path, sample1_path], maybe_paths);
+}
+
+#[test]
+fn fingerprint_of_unchanged_tree_is_stable() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("sample_a.txt"), "a").unwrap();
+    fs::write(root.path().join("sample_b.txt"), "b").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    let first = fingerprint(&find_files_containing_name(root_directory, "sample"), true);
+    let second = fingerprint(&find_files_containing_name(root_directory, "sample"), true);
+    assert_eq!(first, second);
+    assert_eq!(first.len(), 16);
+
+    // The order paths are given in doesn't matter
+    let mut reversed = find_files_containing_name(root_directory, "sample");
+    reversed.reverse();
+    assert_eq!(first, fingerprint(&reversed, true));
+
+    // Adding a matching file changes the fingerprint
+    fs::write(root.path().join("sample_c.txt"), "c").unwrap();
+    let third = fingerprint(&find_files_containing_name(root_directory, "sample"), true);
+    assert_ne!(first, third);
+}
+
+#[test]
+fn fingerprint_with_metadata_sees_content_changes() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("sample.txt");
+    fs::write(&path, "a").unwrap();
+
+    let paths = vec![path.clone()];
+    let names_only = fingerprint(&paths, false);
+    let with_metadata = fingerprint(&paths, true);
+
+    // Growing the file only shows up when sizes are hashed
+    fs::write(&path, "a longer sample").unwrap();
+    assert_eq!(names_only, fingerprint(&paths, false));
+    assert_ne!(with_metadata, fingerprint(&paths, true));
+}
+
+#[test]
+fn tail_of_matched_file() {
+    let maybe_paths = find_files_containing_name("tests/assets", "sample2");
+    assert_eq!(maybe_paths.len(), 1);
+
+    let tail = tail_lines(&maybe_paths[0], 2).unwrap();
+    assert_eq!(tail, vec!["🥸😎😜🥳🤟🙌👾👻🥰", "... how awesome!"]);
+
+    // Asking for more lines than the file has returns the whole file
+    let tail = tail_lines(Path::new("tests/assets/sample1.txt"), 100).unwrap();
+    assert_eq!(tail.len(), 5);
+    assert_eq!(tail[0], "Sample Text");
+}
+
+#[test]
+fn tail_reads_back_across_chunks() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("large.log");
+    let content: String = (0..10_000).map(|x| format!("line {x}\n")).collect();
+    fs::write(&path, content).unwrap();
+
+    let tail = tail_lines(&path, 1000).unwrap();
+    assert_eq!(tail.len(), 1000);
+    assert_eq!(tail[0], "line 9000");
+    assert_eq!(tail[999], "line 9999");
+}
+
+#[test]
+fn tail_skips_binary_files() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("data.bin");
+    fs::write(&path, [0x7f, b'E', b'L', b'F', 0, 1, 2, b'\n']).unwrap();
+
+    let error = tail_lines(&path, 1).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+// Run git in directory, failing the test if it doesn't succeed
+fn git(directory: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn matches_annotated_with_git_status() {
+    let root = tempfile::tempdir().unwrap();
+    let path = |name: &str| root.path().join(name);
+    fs::write(path("sample_clean.txt"), "clean").unwrap();
+    fs::write(path("sample_modified.txt"), "before").unwrap();
+    fs::write(path("sample_staged.txt"), "before").unwrap();
+    git(root.path(), &["init", "--quiet"]);
+    git(root.path(), &["add", "."]);
+    git(root.path(), &["commit", "--quiet", "-m", "Add samples"]);
+
+    fs::write(path("sample_modified.txt"), "after").unwrap();
+    fs::write(path("sample_staged.txt"), "after").unwrap();
+    git(root.path(), &["add", "sample_staged.txt"]);
+    fs::write(path("sample_untracked.txt"), "new").unwrap();
+
+    let root_directory = root.path().to_str().unwrap();
+    let matches = find_files_containing_name(root_directory, "sample_");
+    let statuses: Vec<_> = annotate_git_status(root_directory, &matches)
+        .into_iter()
+        .map(|(path, status)| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, status.to_string())
+        })
+        .collect();
+    let expected = [
+        ("sample_clean.txt", "clean"),
+        ("sample_modified.txt", "modified"),
+        ("sample_staged.txt", "staged"),
+        ("sample_untracked.txt", "untracked"),
+    ];
+    assert_eq!(
+        statuses,
+        expected.map(|(name, status)| (name.to_string(), status.to_string()))
+    );
+}
+
+#[test]
+fn git_status_outside_repository() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("sample.txt"), "a").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    let matches = find_files_containing_name(root_directory, "sample");
+    let annotated = annotate_git_status(root_directory, &matches);
+    assert_eq!(
+        annotated,
+        vec![(root.path().join("sample.txt"), GitStatus::NotInRepository)]
+    );
+}
+
+#[test]
+fn matched_files_copied_to_destination() {
+    let destination = tempfile::tempdir().unwrap();
+    let destination = destination.path().join("gathered");
+
+    // sample1.txt and sample2.txt, plus a second file named sample1.txt
+    let mut files = find_files_containing_name("tests/assets", "sample");
+    files.push(PathBuf::from("tests/assets/sample1.txt"));
+    let copies = copy_files_to("tests/assets", &files, &destination).unwrap();
+
+    let copied: Vec<_> = copies.into_iter().map(|(_, copy)| copy.unwrap()).collect();
+    assert_eq!(
+        copied,
+        vec![
+            destination.join("sample1.txt"),
+            destination.join("sample2.txt"),
+            destination.join("sample1_1.txt"),
+        ]
+    );
+    assert_eq!(
+        fs::read(destination.join("sample1_1.txt")).unwrap(),
+        fs::read("tests/assets/sample1.txt").unwrap()
+    );
+}
+
+#[test]
+fn copy_into_search_root_refused() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("sample.txt"), "a").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+    let files = find_files_containing_name(root_directory, "sample");
+
+    let destination = root.path().join("copies");
+    let error = copy_files_to(root_directory, &files, &destination).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!destination.exists());
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch_reruns_search_on_new_file() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("sample_a.txt"), "a").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    let new_file = root.path().join("sample_b.txt");
+    let writer = {
+        let new_file = new_file.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            fs::write(new_file, "b").unwrap();
+        })
+    };
+
+    let mut searches = Vec::new();
+    let debounce = std::time::Duration::from_millis(50);
+    find_files::find_files::watch_files_containing_name(
+        root_directory,
+        "sample",
+        debounce,
+        |matches| {
+            searches.push(matches);
+            false
+        },
+    )
+    .unwrap();
+    writer.join().unwrap();
+
+    assert_eq!(
+        searches,
+        vec![vec![root.path().join("sample_a.txt"), new_file]]
+    );
+}
+
+#[test]
+fn cached_search_matches_live_search() {
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir(root.path().join("nested")).unwrap();
+    fs::write(root.path().join("sample_a.txt"), "a").unwrap();
+    fs::write(root.path().join("nested/sample_b.txt"), "b").unwrap();
+    fs::write(root.path().join("other.txt"), "c").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    let cache_directory = tempfile::tempdir().unwrap();
+    let cache_path = cache_directory.path().join("index.txt");
+    assert_eq!(build_cache(root_directory, &cache_path).unwrap(), 4);
+
+    let paths = read_cache(root_directory, &cache_path).unwrap();
+    let live = find_files_containing_name(root_directory, "sample");
+    assert_eq!(find_files_in_cache(&paths, "sample"), live);
+    assert_eq!(live.len(), 2);
+}
+
+#[test]
+fn stale_or_foreign_cache_rejected() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join("sample.txt"), "a").unwrap();
+    let root_directory = root.path().to_str().unwrap();
+
+    let cache_directory = tempfile::tempdir().unwrap();
+    let cache_path = cache_directory.path().join("index.txt");
+    build_cache(root_directory, &cache_path).unwrap();
+
+    // Built for a different root
+    let other_root = cache_directory.path().to_str().unwrap();
+    let error = read_cache(other_root, &cache_path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // Adding a file to the root leaves the cache stale
+    let modified = fs::metadata(root.path()).unwrap().modified().unwrap();
+    fs::write(root.path().join("sample_new.txt"), "b").unwrap();
+    let file = fs::File::open(root.path()).unwrap();
+    file.set_modified(modified + std::time::Duration::from_secs(1))
+        .unwrap();
+    let error = read_cache(root_directory, &cache_path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
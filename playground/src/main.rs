@@ -0,0 +1,19 @@
+//! A single binary bundling every tool in the playground.
+//! `playground dice` starts the dice game (taking the game's own arguments after it),
+//! `playground find` the file search.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match std::env::args().nth(1).as_deref() {
+        Some("dice") => dice_roulette::run_dice(std::env::args().skip(2)),
+        Some("find") => {
+            find_files::repl::run_find();
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("Usage: playground <dice [--dice N] [--sides N] [--seed N] [--async]|find>");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+// Run the playground binary with a subcommand, feeding input to stdin
+fn run_playground(subcommand: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_playground"))
+        .args(subcommand)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn dice_subcommand_runs_game_menu() {
+    let output = run_playground(&["dice"], "exit\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Dice Factions!"));
+    assert!(stdout.contains("Hope you enjoyed the game!"));
+}
+
+#[test]
+fn find_subcommand_runs_search() {
+    let output = run_playground(&["find"], "playground_test\nq\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Enter a search term."));
+    assert!(stdout.contains("tests/playground_test.rs"));
+}
+
+#[test]
+fn unknown_subcommand_fails() {
+    let output = run_playground(&["unknown"], "");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Usage"));
+}